@@ -0,0 +1,41 @@
+// バイクのコントラクト(ft_sharing_economy)とファクトリーコントラクト(fleet_factory)で共有する, 都市の初期設定の型
+use near_sdk::{
+    borsh::{self, BorshDeserialize, BorshSerialize},
+    json_types::{U128, U64},
+    serde::{Deserialize, Serialize},
+    AccountId,
+};
+
+// 緯度と経度. 1e-4度単位の固定小数点で, 同じ値の場所を同じ場所として扱います.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Location {
+    pub lat: i32,
+    pub lon: i32,
+}
+
+// 都市ごとのコントラクトの初期設定. ファクトリーコントラクトからnew_with_config()に渡します.
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct CityConfig {
+    // 都市のコントラクトのOwner
+    pub owner_id: AccountId,
+    pub num_of_bikes: usize,
+    // FT_CONTRACT_ACCOUNTでバイクを使用するのに必要なft
+    pub price: U128,
+    // NEARでバイクを使用するのに必要なyoctoNEAR
+    pub near_price: U128,
+    pub inspection_interval: U64,
+    pub rides_per_inspection: u32,
+    pub security_deposit_bps: u32,
+    pub stations: Vec<CityStation>,
+}
+
+// CityConfigで登録するステーション
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct CityStation {
+    pub station_id: String,
+    pub location: Location,
+    pub capacity: u32,
+}
@@ -0,0 +1,26 @@
+use std::{env, fs, path::Path};
+
+// ファクトリーコントラクトに埋め込む, バイクのコントラクト(ft_sharing_economy)のwasm
+const FLEET_WASM: &str = "../target/wasm32-unknown-unknown/release/ft_sharing_economy.wasm";
+
+// wasmをOUT_DIRにコピーします.
+// wasm32向けのビルドではwasmが必要です. それ以外(単体テストなど)でビルドされていない場合は空のファイルを用意します.
+fn main() {
+    println!("cargo:rerun-if-changed={}", FLEET_WASM);
+    let code = match fs::read(FLEET_WASM) {
+        Ok(code) => code,
+        Err(_) if env::var("CARGO_CFG_TARGET_ARCH").as_deref() == Ok("wasm32") => panic!(
+            "{} is not built. Build ft_sharing_economy for wasm32-unknown-unknown before the factory",
+            FLEET_WASM
+        ),
+        Err(_) => {
+            println!(
+                "cargo:warning={} is not built, the factory embeds an empty contract",
+                FLEET_WASM
+            );
+            Vec::new()
+        }
+    };
+    let out_dir = env::var("OUT_DIR").unwrap();
+    fs::write(Path::new(&out_dir).join("ft_sharing_economy.wasm"), code).unwrap();
+}
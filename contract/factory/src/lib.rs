@@ -0,0 +1,294 @@
+use near_sdk::{
+    borsh::{self, BorshDeserialize, BorshSerialize},
+    collections::UnorderedMap,
+    env,
+    json_types::{U128, U64},
+    log, near_bindgen,
+    serde::{Deserialize, Serialize},
+    serde_json::{self, json},
+    AccountId, Balance, BorshStorageKey, Gas, PanicOnDefault, Promise, PromiseResult,
+};
+
+// 都市ごとにデプロイするバイクのコントラクト(ft_sharing_economy)のwasm. build.rsで用意します.
+const FLEET_CODE: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/ft_sharing_economy.wasm"));
+// wasmに加えて, 都市のコントラクトの状態のために用意するストレージ(バイト)
+const FLEET_STATE_STORAGE: u64 = 50_000;
+const GAS_FOR_FLEET_INIT: Gas = Gas(50_000_000_000_000);
+const GAS_FOR_CALLBACK: Gas = Gas(10_000_000_000_000);
+
+// 都市のコントラクトの初期設定. バイクのコントラクトと共有し, new_with_config()に渡します.
+pub use city_config::{CityConfig, CityStation, Location};
+
+// デプロイした都市のコントラクト
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct Fleet {
+    account_id: AccountId,
+    // 都市のコントラクトの管理者
+    owner_id: AccountId,
+    created_at: u64,
+}
+
+// get_fleet()やfleets()の返り値
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct FleetView {
+    pub city: String,
+    pub account_id: AccountId,
+    pub owner_id: AccountId,
+    pub created_at: U64,
+}
+
+// コレクションがストレージ上で使用するキーのプレフィックス
+#[derive(BorshStorageKey, BorshSerialize)]
+enum StorageKey {
+    Fleets,
+}
+
+// コントラクトの定義
+#[near_bindgen]
+#[derive(BorshDeserialize, BorshSerialize, PanicOnDefault)]
+pub struct Contract {
+    owner_id: AccountId,
+    // 都市の名前と, デプロイした都市のコントラクト
+    fleets: UnorderedMap<String, Fleet>,
+}
+
+#[near_bindgen]
+impl Contract {
+    #[init]
+    pub fn new() -> Self {
+        log!("initialize fleet factory");
+        Self {
+            // コントラクトを初期化したアカウントを管理者とします.
+            owner_id: env::predecessor_account_id(),
+            fleets: UnorderedMap::new(StorageKey::Fleets),
+        }
+    }
+
+    pub fn owner_id(&self) -> AccountId {
+        self.owner_id.clone()
+    }
+
+    // 都市のコントラクトのデプロイに必要なyoctoNEAR
+    pub fn required_deposit(&self) -> U128 {
+        U128::from(
+            (FLEET_CODE.len() as u64 + FLEET_STATE_STORAGE) as Balance * env::storage_byte_cost(),
+        )
+    }
+
+    pub fn num_of_fleets(&self) -> u64 {
+        self.fleets.len()
+    }
+
+    pub fn get_fleet(&self, city: String) -> Option<FleetView> {
+        self.fleets
+            .get(&city)
+            .map(|fleet| Self::fleet_view(city, fleet))
+    }
+
+    pub fn fleets(&self, from_index: u64, limit: u64) -> Vec<FleetView> {
+        self.fleets
+            .iter()
+            .skip(from_index as usize)
+            .take(limit as usize)
+            .map(|(city, fleet)| Self::fleet_view(city, fleet))
+            .collect()
+    }
+
+    // サブアカウント<city>.<このコントラクト>を作成してバイクのコントラクトをデプロイし, configで初期化します.
+    // 添付されたNEARは都市のコントラクトのストレージに使用します. 管理者のみ呼び出せます.
+    #[payable]
+    pub fn create_fleet(&mut self, city: String, config: CityConfig) -> Promise {
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.owner_id,
+            "Only the owner can call this method"
+        );
+        assert!(self.fleets.get(&city).is_none(), "Fleet already exists");
+        let required_deposit = self.required_deposit().0;
+        let deposit = env::attached_deposit();
+        assert!(
+            deposit >= required_deposit,
+            "Require {} yoctoNEAR to deploy the fleet",
+            required_deposit
+        );
+        let account_id: AccountId = format!("{}.{}", city, env::current_account_id())
+            .parse()
+            .expect("Invalid city name");
+        log!("create fleet {} at {}", &city, &account_id);
+
+        let owner_id = config.owner_id.clone();
+        Promise::new(account_id.clone())
+            .create_account()
+            .transfer(deposit)
+            .deploy_contract(FLEET_CODE.to_vec())
+            .function_call(
+                "new_with_config".to_string(),
+                serde_json::to_vec(&json!({ "config": config })).unwrap(),
+                0,
+                GAS_FOR_FLEET_INIT,
+            )
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_CALLBACK)
+                    .callback_create_fleet(
+                        city,
+                        account_id,
+                        owner_id,
+                        env::predecessor_account_id(),
+                        U128::from(deposit),
+                    ),
+            )
+    }
+
+    // callback
+    // 都市のコントラクトのデプロイに成功した場合のみ登録し, 失敗した場合は添付されたNEARを返金します.
+    #[private]
+    pub fn callback_create_fleet(
+        &mut self,
+        city: String,
+        account_id: AccountId,
+        owner_id: AccountId,
+        creator_id: AccountId,
+        deposit: U128,
+    ) -> bool {
+        assert_eq!(env::promise_results_count(), 1, "This is a callback method");
+        match env::promise_result(0) {
+            PromiseResult::NotReady => unreachable!(),
+            PromiseResult::Failed => {
+                log!("Fail to create fleet {}", &city);
+                Promise::new(creator_id).transfer(deposit.0);
+                false
+            }
+            PromiseResult::Successful(_) => {
+                log!("fleet {} is created", &city);
+                self.fleets.insert(
+                    &city,
+                    &Fleet {
+                        account_id,
+                        owner_id,
+                        created_at: env::block_timestamp(),
+                    },
+                );
+                true
+            }
+        }
+    }
+}
+
+impl Contract {
+    fn fleet_view(city: String, fleet: Fleet) -> FleetView {
+        FleetView {
+            city,
+            account_id: fleet.account_id,
+            owner_id: fleet.owner_id,
+            created_at: U64::from(fleet.created_at),
+        }
+    }
+}
+
+// トランザクションの実行環境をシュミレーション
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::testing_env;
+
+    use super::*;
+
+    fn get_context(predecessor_account_id: AccountId) -> VMContextBuilder {
+        let mut builder = VMContextBuilder::new();
+        builder
+            .current_account_id("factory.testnet".parse().unwrap())
+            .signer_account_id(predecessor_account_id.clone())
+            .predecessor_account_id(predecessor_account_id);
+        builder
+    }
+
+    fn testing_env_with_promise_result(context: &mut VMContextBuilder, result: PromiseResult) {
+        testing_env!(
+            context.build(),
+            near_sdk::VMConfig::test(),
+            near_sdk::RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![result]
+        );
+    }
+
+    fn city_config() -> CityConfig {
+        CityConfig {
+            owner_id: accounts(2),
+            num_of_bikes: 5,
+            price: U128::from(30),
+            near_price: U128::from(10u128.pow(23)),
+            inspection_interval: U64::from(0),
+            rides_per_inspection: 10,
+            security_deposit_bps: 0,
+            stations: vec![CityStation {
+                station_id: "central".to_string(),
+                location: Location { lat: 0, lon: 0 },
+                capacity: 10,
+            }],
+        }
+    }
+
+    // デプロイに成功した都市のコントラクトが登録されることを確認
+    #[test]
+    fn create_fleet() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new();
+        let deposit = contract.required_deposit().0;
+        testing_env!(context.attached_deposit(deposit).build());
+        contract.create_fleet("tokyo".to_string(), city_config());
+        assert_eq!(contract.num_of_fleets(), 0);
+
+        testing_env_with_promise_result(
+            context.predecessor_account_id("factory.testnet".parse().unwrap()),
+            PromiseResult::Successful(vec![]),
+        );
+        let account_id: AccountId = "tokyo.factory.testnet".parse().unwrap();
+        assert!(contract.callback_create_fleet(
+            "tokyo".to_string(),
+            account_id.clone(),
+            accounts(2),
+            accounts(1),
+            U128::from(deposit),
+        ));
+        let fleet = contract.get_fleet("tokyo".to_string()).unwrap();
+        assert_eq!(fleet.account_id, account_id);
+        assert_eq!(fleet.owner_id, accounts(2));
+        assert_eq!(contract.fleets(0, 10).len(), 1);
+    }
+
+    // デプロイに失敗した場合は登録されないことを確認
+    #[test]
+    fn create_fleet_failed() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new();
+
+        testing_env_with_promise_result(
+            context.predecessor_account_id("factory.testnet".parse().unwrap()),
+            PromiseResult::Failed,
+        );
+        assert!(!contract.callback_create_fleet(
+            "tokyo".to_string(),
+            "tokyo.factory.testnet".parse().unwrap(),
+            accounts(2),
+            accounts(1),
+            U128::from(1),
+        ));
+        assert!(contract.get_fleet("tokyo".to_string()).is_none());
+    }
+
+    // デプロイに必要なNEARが足りない->パニックを起こすか確認
+    #[test]
+    #[should_panic(expected = "to deploy the fleet")]
+    fn create_fleet_without_deposit() {
+        let context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new();
+
+        contract.create_fleet("tokyo".to_string(), city_config());
+    }
+}
@@ -0,0 +1,244 @@
+use near_sdk::{
+    borsh::{self, BorshDeserialize, BorshSerialize},
+    env, log, near_bindgen,
+    serde::{Deserialize, Serialize},
+};
+
+use crate::*;
+
+// 一度の呼び出しで処理できるバイクの台数の上限. ガスの使用量を抑えるために使用します.
+const MAX_BATCH_SIZE: u32 = 50;
+
+// バイクの識別情報
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BikeMetadata {
+    pub serial_number: String,
+    pub model: String,
+}
+
+// add_bikes()で追加するバイク. station_idを指定した場合はそのステーションに, 指定しない場合は空きがあるステーションに置きます.
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct NewBike {
+    pub metadata: BikeMetadata,
+    pub station_id: Option<String>,
+}
+
+// 一括処理のバイクごとの結果
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BatchResult {
+    pub index: usize,
+    // 処理の内容. 例: "added", "maintenance", "returned from alice.testnet"
+    pub result: String,
+}
+
+#[near_bindgen]
+impl Contract {
+    pub fn bike_metadata(&self, index: usize) -> Option<BikeMetadata> {
+        self.bikes[index].metadata.clone()
+    }
+
+    // コントラクトが所有するバイクをまとめて追加します. ManageFleetの権限が必要です.
+    // バイクの情報(識別情報を含む)のストレージは呼び出したアカウントが負担します.
+    // 1台でも追加できない場合は全て追加しません. limitを超える台数は指定できません.
+    pub fn add_bikes(&mut self, bikes: Vec<NewBike>, limit: u32) -> Vec<BatchResult> {
+        self.assert_permission(Permission::ManageFleet);
+        assert_batch_size(bikes.len(), limit);
+        let mut added_to_station: Vec<&str> = Vec::new();
+        let errors: Vec<(usize, String)> = bikes
+            .iter()
+            .enumerate()
+            .filter_map(|(i, bike)| {
+                let error = match &bike.station_id {
+                    Some(station_id) => {
+                        let error = self.check_station_capacity(station_id, &added_to_station);
+                        added_to_station.push(station_id);
+                        error
+                    }
+                    None => None,
+                };
+                error.map(|error| (self.bikes.len() + i, error))
+            })
+            .collect();
+        assert_batch_succeeded(errors);
+
+        let results: Vec<BatchResult> = bikes
+            .into_iter()
+            .map(|bike| {
+                let index = self.bikes.len();
+                log!(
+                    "add bike {}: {} {}",
+                    index,
+                    &bike.metadata.serial_number,
+                    &bike.metadata.model
+                );
+                let mut info = BikeInfo::new(None, 0);
+                info.metadata = Some(bike.metadata);
+                info.station_id = bike.station_id;
+                self.bikes.push(info);
+                BatchResult {
+                    index,
+                    result: "added".to_string(),
+                }
+            })
+            .collect();
+        // station_idを指定していないバイクを置いてから, 置いたステーションを含めたストレージを負担させます.
+        self.place_bikes();
+        let predecessor_id = env::predecessor_account_id();
+        for result in &results {
+            let bytes = storage_size(&self.bikes[result.index]);
+            self.charge_storage_bytes(&predecessor_id, bytes);
+        }
+        results
+    }
+
+    // バイク 使用可 -> 修理中
+    // 使用可能なバイクをまとめて修理中にします. ManageFleetの権限が必要です.
+    // 1台でも修理中にできない場合は全て変更しません. limitを超える台数は指定できません.
+    pub fn start_maintenance_batch(&mut self, indices: Vec<usize>, limit: u32) -> Vec<BatchResult> {
+        self.assert_permission(Permission::ManageFleet);
+        assert_batch_size(indices.len(), limit);
+        let errors = self.check_bikes(&indices, |bike| match bike.state {
+            Bike::Available => None,
+            _ => Some("Bike is not available".to_string()),
+        });
+        assert_batch_succeeded(errors);
+
+        indices
+            .into_iter()
+            .map(|index| {
+                let predecessor_id = env::predecessor_account_id();
+                log!("{} puts bike {} into maintenance", &predecessor_id, index);
+                self.set_state(index, Bike::Maintenance(predecessor_id));
+                BatchResult {
+                    index,
+                    result: "maintenance".to_string(),
+                }
+            })
+            .collect()
+    }
+
+    // バイク 使用中or点検中 -> 使用可
+    // 返却されていないバイクをまとめてステーションに返却します. ManageFleetの権限が必要です.
+    // 使用中のバイクは使用料を精算し, 点検中のバイクは点検の報酬を支払わずに取り分けた報酬を戻します.
+    // 1台でも返却できない場合は全て返却しません. limitを超える台数は指定できません.
+    pub fn force_return_bikes(
+        &mut self,
+        indices: Vec<usize>,
+        station_id: String,
+        limit: u32,
+    ) -> Vec<BatchResult> {
+        self.assert_permission(Permission::ManageFleet);
+        assert_batch_size(indices.len(), limit);
+        assert!(self.stations.get(&station_id).is_some(), "Unknown station");
+        let mut errors = self.check_bikes(&indices, |bike| match bike.state {
+            Bike::InUse(_) => None,
+            Bike::Inspection(_) if bike.pending_reward.is_none() => {
+                Some("Reward payout is in progress".to_string())
+            }
+            Bike::Inspection(_) => None,
+            _ => Some("Bike is not in use or inspection".to_string()),
+        });
+        let returned = vec![station_id.as_str(); indices.len()];
+        for (i, &index) in indices.iter().enumerate() {
+            if let Some(error) = self.check_station_capacity(&station_id, &returned[..i]) {
+                errors.push((index, error));
+            }
+        }
+        assert_batch_succeeded(errors);
+
+        indices
+            .into_iter()
+            .map(|index| {
+                let result = match &self.bikes[index].state {
+                    Bike::InUse(user) => {
+                        let user = user.clone();
+                        self.settle_payment(index);
+                        let bike = &mut self.bikes[index];
+                        bike.rides_since_inspection += 1;
+                        bike.last_user = Some(user.clone());
+                        format!("returned from {}", user)
+                    }
+                    Bike::Inspection(inspector) => {
+                        let inspector = inspector.clone();
+                        let reward = self.bikes[index].pending_reward.take().unwrap();
+                        self.treasury.release_reward(reward);
+                        format!("inspection cancelled for {}", inspector)
+                    }
+                    _ => unreachable!(),
+                };
+                log!(
+                    "force return bike {} to {}: {}",
+                    index,
+                    &station_id,
+                    &result
+                );
+                self.bikes[index].station_id = Some(station_id.clone());
+                self.set_state(index, Bike::Available);
+                BatchResult { index, result }
+            })
+            .collect()
+    }
+}
+
+impl Contract {
+    // バイクごとに処理できるかを確認し, 処理できないバイクのindexと理由を返します.
+    fn check_bikes(
+        &self,
+        indices: &[usize],
+        check: impl Fn(&BikeInfo) -> Option<String>,
+    ) -> Vec<(usize, String)> {
+        let mut errors = Vec::new();
+        for (i, &index) in indices.iter().enumerate() {
+            let error = if indices[..i].contains(&index) {
+                Some("Bike is duplicated".to_string())
+            } else {
+                self.bikes
+                    .get(index)
+                    .map_or(Some("Bike does not exist".to_string()), &check)
+            };
+            if let Some(error) = error {
+                errors.push((index, error));
+            }
+        }
+        errors
+    }
+
+    // 同じ呼び出しで先に置くバイク(added)を含めて, ステーションにもう1台置けるかを確認します.
+    fn check_station_capacity(&self, station_id: &str, added: &[&str]) -> Option<String> {
+        let station = match self.stations.get(&station_id.to_string()) {
+            Some(station) => station,
+            None => return Some("Unknown station".to_string()),
+        };
+        let num_of_bikes = self.num_of_bikes_at(station_id)
+            + added.iter().filter(|added| **added == station_id).count() as u32;
+        if num_of_bikes >= station.capacity {
+            Some("Station is full".to_string())
+        } else {
+            None
+        }
+    }
+}
+
+fn assert_batch_size(size: usize, limit: u32) {
+    assert!(
+        limit <= MAX_BATCH_SIZE,
+        "Limit must not exceed {}",
+        MAX_BATCH_SIZE
+    );
+    assert!(size as u32 <= limit, "Batch size exceeds the limit");
+}
+
+// 1台でも処理できない場合は, バイクごとの理由を示してpanicします.
+fn assert_batch_succeeded(errors: Vec<(usize, String)>) {
+    if errors.is_empty() {
+        return;
+    }
+    let errors: Vec<String> = errors
+        .into_iter()
+        .map(|(index, error)| format!("bike {}: {}", index, error))
+        .collect();
+    panic!("Batch failed: {}", errors.join(", "));
+}
@@ -0,0 +1,33 @@
+use near_sdk::{env, log, near_bindgen};
+
+use crate::*;
+
+// 都市ごとのコントラクトの初期設定. ファクトリーコントラクトと共有します.
+pub use city_config::{CityConfig, CityStation};
+
+#[near_bindgen]
+impl Contract {
+    // 都市の設定でコントラクトを初期化し, 設定したowner_idをOwnerとします.
+    // バイクは設定したステーションに順に置きます.
+    #[init]
+    pub fn new_with_config(config: CityConfig) -> Self {
+        // 初期化したアカウントをOwnerとして設定を行い, 最後にOwnerを変更します.
+        let mut this = Self::new_without_stations(config.num_of_bikes);
+        this.add_accepted_token(base_token_id(), config.price);
+        this.set_near_price(config.near_price);
+        this.set_inspection_schedule(config.inspection_interval, config.rides_per_inspection);
+        this.set_security_deposit_bps(config.security_deposit_bps);
+        for station in config.stations {
+            this.add_station(station.station_id, station.location, station.capacity);
+        }
+        this.place_bikes();
+        // 初期化したアカウント自身をowner_idとした場合はOwnerのままとします.
+        let predecessor_id = env::predecessor_account_id();
+        if config.owner_id != predecessor_id {
+            log!("transfer ownership to {}", &config.owner_id);
+            this.add_role(&config.owner_id, Role::Owner);
+            this.remove_role(&predecessor_id, Role::Owner);
+        }
+        this
+    }
+}
@@ -0,0 +1,381 @@
+use near_sdk::{
+    borsh::{self, BorshDeserialize, BorshSerialize},
+    env,
+    json_types::U128,
+    log, near_bindgen,
+    serde::{Deserialize, Serialize},
+    serde_json, AccountId, Promise, PromiseResult,
+};
+
+use crate::*;
+
+// 預り金の割合の基準(ベーシスポイント, 10000 = 100%)
+const MAX_BPS: u128 = 10000;
+
+// バイクの使用時に使用料と合わせて受け取る預り金.
+// バイクの使用後, 次の点検でバイクに問題がないことが確認されるまで保持します.
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct Deposit {
+    account_id: AccountId,
+    currency: Currency,
+    amount: u128,
+    // 預り金に対して開かれている異議申し立て. 解決するまで預り金を保持します.
+    dispute_id: Option<u64>,
+}
+
+// deposits_of()の返り値
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct DepositView {
+    pub account_id: AccountId,
+    pub currency: Currency,
+    pub amount: U128,
+    pub dispute_id: Option<u64>,
+}
+
+#[near_bindgen]
+impl Contract {
+    // 使用料のうち預り金として受け取る割合(ベーシスポイント)
+    pub fn security_deposit_bps(&self) -> u32 {
+        self.security_deposit_bps
+    }
+
+    // バイクに対して保持している預り金
+    pub fn deposits_of(&self, index: usize) -> Vec<DepositView> {
+        self.held_deposits_of(index)
+            .iter()
+            .map(|deposit| DepositView {
+                account_id: deposit.account_id.clone(),
+                currency: deposit.currency.clone(),
+                amount: U128::from(deposit.amount),
+                dispute_id: deposit.dispute_id,
+            })
+            .collect()
+    }
+
+    // 点検が完了し, account_idが引き出せる預り金
+    pub fn deposit_balance_of(&self, account_id: AccountId, currency: Currency) -> U128 {
+        U128::from(
+            self.deposit_balances
+                .get(&(account_id, currency))
+                .unwrap_or(0),
+        )
+    }
+
+    // 預り金の割合を設定します. ManageSettingsの権限が必要です.
+    pub fn set_security_deposit_bps(&mut self, security_deposit_bps: u32) {
+        self.assert_governed(Permission::ManageSettings);
+        log!("set security deposit: {}", security_deposit_bps);
+        self.security_deposit_bps = security_deposit_bps;
+    }
+
+    // 修理中のバイクに対して保持している, account_idの預り金からamountを受け取ります. ClaimDepositsの権限が必要です.
+    // 修理の報告で責任があるとされたアカウントの預り金は, 修理の完了後も報告が精算されるまで受け取れます.
+    // 受け取った預り金は支払いに使用された通貨の使用料として記録します.
+    pub fn claim_deposit(&mut self, index: usize, account_id: AccountId, amount: U128) {
+        self.assert_governed(Permission::ClaimDeposits);
+        let reported = self.bikes[index]
+            .damage_report
+            .as_ref()
+            .filter(|report| report.renter_id == account_id);
+        assert!(
+            matches!(self.bikes[index].state, Bike::Maintenance(_)) || reported.is_some(),
+            "Deposit can only be claimed for a bike in maintenance"
+        );
+        // 修理の報告で責任があるとされたアカウントは, 一定時間内であれば異議を申し立てられます.
+        if let Some(report) = reported {
+            assert!(
+                env::block_timestamp() > report.reported_at + self.dispute_window,
+                "Dispute window has not passed"
+            );
+        }
+        let mut deposits = self.held_deposits_of(index);
+        let deposit = deposits
+            .iter_mut()
+            .find(|deposit| deposit.account_id == account_id)
+            .expect("No deposit of the account");
+        assert!(deposit.dispute_id.is_none(), "Deposit is under dispute");
+        assert!(deposit.amount >= amount.0, "Not enough deposit");
+        deposit.amount -= amount.0;
+        let currency = deposit.currency.clone();
+        log!(
+            "claim deposit of {} for bike {}: {} {:?}",
+            &account_id,
+            index,
+            amount.0,
+            currency
+        );
+        self.set_held_deposits(index, deposits);
+
+        self.receive_deposit(currency, amount.0);
+        self.penalize_damage(&account_id);
+        // 預り金を受け取ったため, 修理の報告は精算済みとします. 残りの預り金は次の点検や修理の完了で引き出せます.
+        self.settle_damage_report(index, &account_id);
+    }
+
+    // 点検が完了し, 引き出せる預り金を全て送信します.
+    pub fn withdraw_deposit(&mut self, currency: Currency) -> Promise {
+        let predecessor_id = env::predecessor_account_id();
+        let initial_storage = env::storage_usage();
+        let amount = self
+            .deposit_balances
+            .remove(&(predecessor_id.clone(), currency.clone()))
+            .expect("No deposit to withdraw");
+        self.track_storage(&predecessor_id, initial_storage);
+        log!(
+            "{} withdraws deposit: {} {:?}",
+            &predecessor_id,
+            amount,
+            currency
+        );
+        let transfer = match &currency {
+            Currency::Near => return Promise::new(predecessor_id).transfer(amount),
+            Currency::Ft(token_id) if *token_id == base_token_id() => self.transfer_from_treasury(
+                TreasuryAccount::Deposits,
+                predecessor_id.clone(),
+                amount,
+            ),
+            Currency::Ft(token_id) => self.cross_contract_call_transfer(
+                token_id,
+                predecessor_id.to_string(),
+                amount.to_string(),
+            ),
+        };
+        transfer.then(
+            Self::ext(env::current_account_id())
+                .with_static_gas(GAS_FOR_CALLBACK)
+                .callback_withdraw_deposit(predecessor_id, currency, U128::from(amount)),
+        )
+    }
+
+    // callback
+    // 送信が失敗した場合, 引き出せる預り金を元に戻します.
+    // FT_CONTRACT_ACCOUNTの場合は, 直前のcallback_refund_treasury()の返り値(送信が成功したか)を受け取ります.
+    #[private]
+    pub fn callback_withdraw_deposit(
+        &mut self,
+        account_id: AccountId,
+        currency: Currency,
+        amount: U128,
+    ) {
+        assert_eq!(env::promise_results_count(), 1, "This is a callback method");
+        let transferred = match env::promise_result(0) {
+            PromiseResult::NotReady => unreachable!(),
+            PromiseResult::Failed => false,
+            PromiseResult::Successful(value) => {
+                currency != Currency::Ft(base_token_id())
+                    || serde_json::from_slice::<bool>(&value).unwrap_or(false)
+            }
+        };
+        if !transferred {
+            log!("refund deposit to {}: {}", &account_id, amount.0);
+            self.add_deposit_balance(account_id, currency, amount.0);
+        }
+    }
+}
+
+impl Contract {
+    // 使用料に対する預り金
+    pub(crate) fn security_deposit(&self, price: u128) -> u128 {
+        price * self.security_deposit_bps as u128 / MAX_BPS
+    }
+
+    // 借りたステーションと異なるステーションへ返却した場合の手数料を, 使用料の通貨の量に換算します.
+    pub(crate) fn rebalancing_fee(&self, currency: &Currency) -> u128 {
+        let amount = match &self.rebalancing_config.adjustment {
+            RebalancingAdjustment::Fee { amount } => amount.0,
+            _ => return 0,
+        };
+        let base_price = self.accepted_tokens.get(&base_token_id()).unwrap();
+        let price = match currency {
+            Currency::Near => self.near_price,
+            Currency::Ft(token_id) => self.accepted_tokens.get(token_id).unwrap_or(base_price),
+        };
+        amount * price / base_price
+    }
+
+    // 使用時に受け取る預り金. 異なるステーションへ返却した場合の手数料を含みます.
+    pub(crate) fn rental_deposit(&self, currency: &Currency, price: u128) -> u128 {
+        self.security_deposit(price) + self.rebalancing_fee(currency)
+    }
+
+    // 預り金をバイクに対して保持します.
+    // 同じアカウントと通貨の異議申し立てのない預り金がある場合はまとめ, 新たな預り金のストレージはアカウントが負担します.
+    pub(crate) fn hold_deposit(
+        &mut self,
+        index: usize,
+        account_id: AccountId,
+        currency: Currency,
+        amount: u128,
+    ) {
+        if amount == 0 {
+            return;
+        }
+        if currency == Currency::Ft(base_token_id()) {
+            self.treasury.receive(TreasuryAccount::Deposits, amount);
+        }
+        let mut deposits = self.held_deposits_of(index);
+        match deposits.iter_mut().find(|deposit| {
+            deposit.account_id == account_id
+                && deposit.currency == currency
+                && deposit.dispute_id.is_none()
+        }) {
+            Some(deposit) => deposit.amount += amount,
+            None => {
+                let deposit = Deposit {
+                    account_id,
+                    currency,
+                    amount,
+                    dispute_id: None,
+                };
+                self.charge_storage_bytes(&deposit.account_id, storage_size(&deposit));
+                deposits.push(deposit);
+            }
+        }
+        self.held_deposits.insert(&(index as u64), &deposits);
+    }
+
+    // 使用料を支払わない使用(定期券, ポイント)でも, NEARでの使用料に対する預り金を添付されたNEARで受け取ります.
+    // 預り金を超えて添付されたNEARは返金します.
+    pub(crate) fn hold_attached_deposit(&mut self, index: usize, account_id: &AccountId) {
+        let attached = env::attached_deposit();
+        let security_deposit = self.rental_deposit(&Currency::Near, self.near_price);
+        assert!(
+            attached >= security_deposit,
+            "Require {} yoctoNEAR as the security deposit",
+            security_deposit
+        );
+        self.hold_deposit(index, account_id.clone(), Currency::Near, security_deposit);
+        if attached > security_deposit {
+            Promise::new(account_id.clone()).transfer(attached - security_deposit);
+        }
+    }
+
+    fn held_deposits_of(&self, index: usize) -> Vec<Deposit> {
+        self.held_deposits.get(&(index as u64)).unwrap_or_default()
+    }
+
+    // 保持している預り金を更新します. 量が0になった預り金は削除し, そのストレージを解放します.
+    fn set_held_deposits(&mut self, index: usize, deposits: Vec<Deposit>) {
+        let (held, removed): (Vec<_>, Vec<_>) =
+            deposits.into_iter().partition(|deposit| deposit.amount > 0);
+        for deposit in removed {
+            self.release_storage_bytes(&deposit.account_id, storage_size(&deposit));
+        }
+        if held.is_empty() {
+            self.held_deposits.remove(&(index as u64));
+        } else {
+            self.held_deposits.insert(&(index as u64), &held);
+        }
+    }
+
+    // 点検でバイクに問題がないことが確認された際に, 保持している預り金を引き出せるようにします.
+    // 異議申し立てが開かれている預り金は, 解決するまで保持します.
+    // 修理の報告で責任があるとされたアカウントの預り金は, 受け取られるか異議申し立てができる時間を過ぎるまで保持します.
+    pub(crate) fn release_deposits(&mut self, index: usize) {
+        let blamed_renter_id = self.blamed_renter(index);
+        if blamed_renter_id.is_none() {
+            self.expire_damage_report(index);
+        }
+        let mut deposits = self.held_deposits_of(index);
+        for deposit in deposits.iter_mut() {
+            if deposit.dispute_id.is_none()
+                && blamed_renter_id.as_ref() != Some(&deposit.account_id)
+            {
+                let amount = std::mem::take(&mut deposit.amount);
+                self.add_deposit_balance(
+                    deposit.account_id.clone(),
+                    deposit.currency.clone(),
+                    amount,
+                );
+            }
+        }
+        self.set_held_deposits(index, deposits);
+    }
+
+    pub(crate) fn has_disputed_deposit(&self, index: usize, account_id: &AccountId) -> bool {
+        self.held_deposits_of(index)
+            .iter()
+            .any(|deposit| deposit.account_id == *account_id && deposit.dispute_id.is_some())
+    }
+
+    // account_idの預り金に異議申し立てを記録し, 預り金の量を返します.
+    pub(crate) fn dispute_deposit(
+        &mut self,
+        index: usize,
+        account_id: &AccountId,
+        dispute_id: u64,
+    ) -> u128 {
+        let mut deposits = self.held_deposits_of(index);
+        let deposit = deposits
+            .iter_mut()
+            .find(|deposit| deposit.account_id == *account_id && deposit.dispute_id.is_none())
+            .expect("No deposit of the account");
+        deposit.dispute_id = Some(dispute_id);
+        let amount = deposit.amount;
+        self.held_deposits.insert(&(index as u64), &deposits);
+        amount
+    }
+
+    // 異議申し立てが解決した預り金を, releaseがtrueの場合はバイクを使用したアカウントが引き出せるようにし,
+    // falseの場合は使用料として受け取ります.
+    pub(crate) fn settle_disputed_deposit(&mut self, index: usize, dispute_id: u64, release: bool) {
+        let mut deposits = self.held_deposits_of(index);
+        let deposit = deposits
+            .iter_mut()
+            .find(|deposit| deposit.dispute_id == Some(dispute_id))
+            .expect("No deposit under the dispute");
+        let amount = std::mem::take(&mut deposit.amount);
+        let (account_id, currency) = (deposit.account_id.clone(), deposit.currency.clone());
+        self.set_held_deposits(index, deposits);
+        if release {
+            self.add_deposit_balance(account_id, currency, amount);
+        } else {
+            self.receive_deposit(currency, amount);
+        }
+    }
+
+    // account_idがバイクを使用した際の預り金から, 異なるステーションへ返却した場合の手数料を受け取り, 受け取った量を返します.
+    pub(crate) fn charge_rebalancing_fee(&mut self, index: usize, account_id: &AccountId) -> u128 {
+        let mut deposits = self.held_deposits_of(index);
+        let deposit = match deposits
+            .iter_mut()
+            .rev()
+            .find(|deposit| deposit.account_id == *account_id && deposit.dispute_id.is_none())
+        {
+            Some(deposit) => deposit,
+            None => return 0,
+        };
+        let currency = deposit.currency.clone();
+        let amount = self.rebalancing_fee(&currency).min(deposit.amount);
+        deposit.amount -= amount;
+        self.set_held_deposits(index, deposits);
+        self.receive_deposit(currency, amount);
+        amount
+    }
+
+    // 預り金を支払いに使用された通貨の使用料として受け取ります.
+    pub(crate) fn receive_deposit(&mut self, currency: Currency, amount: u128) {
+        match currency {
+            Currency::Near => self.near_revenue += amount,
+            Currency::Ft(token_id) if token_id == base_token_id() => {
+                self.treasury.withdraw(TreasuryAccount::Deposits, amount);
+                self.treasury.deposit(TreasuryAccount::Revenue, amount);
+            }
+            Currency::Ft(token_id) => self.add_token_revenue(&token_id, amount),
+        }
+    }
+
+    pub(crate) fn add_deposit_balance(
+        &mut self,
+        account_id: AccountId,
+        currency: Currency,
+        amount: u128,
+    ) {
+        let initial_storage = env::storage_usage();
+        let key = (account_id, currency);
+        let balance = self.deposit_balances.get(&key).unwrap_or(0);
+        self.deposit_balances.insert(&key, &(balance + amount));
+        self.track_storage(&key.0, initial_storage);
+    }
+}
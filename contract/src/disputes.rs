@@ -0,0 +1,260 @@
+use near_sdk::{
+    borsh::{self, BorshDeserialize, BorshSerialize},
+    env,
+    json_types::{U128, U64},
+    log, near_bindgen,
+    serde::{Deserialize, Serialize},
+    serde_json::{self, json},
+    AccountId,
+};
+
+use crate::*;
+
+// 点検者による修理の報告. バイクを最後に使用したアカウントに責任があるものとして記録します.
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct DamageReport {
+    inspector_id: AccountId,
+    pub(crate) renter_id: AccountId,
+    pub(crate) reported_at: u64,
+}
+
+// 異議申し立ての状態
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum DisputeStatus {
+    Open,
+    Upheld,   // 異議が認められ, 預り金はバイクを使用したアカウントが引き出せます.
+    Rejected, // 異議が認められず, 預り金は使用料として受け取ります.
+}
+
+// 修理の報告に対する, バイクを使用したアカウントの異議申し立て
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct Dispute {
+    index: usize,
+    renter_id: AccountId,
+    inspector_id: AccountId,
+    reason: String,
+    amount: u128,
+    status: DisputeStatus,
+    opened_at: u64,
+    // 異議申し立てを解決したアカウントと時刻
+    resolved_by: Option<AccountId>,
+    resolved_at: Option<u64>,
+}
+
+// get_dispute()の返り値
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct DisputeView {
+    pub index: usize,
+    pub renter_id: AccountId,
+    pub inspector_id: AccountId,
+    pub reason: String,
+    pub amount: U128,
+    pub status: DisputeStatus,
+    pub opened_at: U64,
+    pub resolved_by: Option<AccountId>,
+    pub resolved_at: Option<U64>,
+}
+
+#[near_bindgen]
+impl Contract {
+    pub fn get_dispute(&self, dispute_id: u64) -> Option<DisputeView> {
+        self.disputes.get(&dispute_id).map(|dispute| DisputeView {
+            index: dispute.index,
+            renter_id: dispute.renter_id,
+            inspector_id: dispute.inspector_id,
+            reason: dispute.reason,
+            amount: U128::from(dispute.amount),
+            status: dispute.status,
+            opened_at: U64::from(dispute.opened_at),
+            resolved_by: dispute.resolved_by,
+            resolved_at: dispute.resolved_at.map(U64::from),
+        })
+    }
+
+    // 修理の報告から異議申し立てができるまでの時間(ナノ秒)
+    pub fn dispute_window(&self) -> U64 {
+        U64::from(self.dispute_window)
+    }
+
+    pub fn arbiters(&self) -> Vec<AccountId> {
+        self.accounts_with_role(Role::Arbiter)
+    }
+
+    // 異議申し立てができるまでの時間を設定します. ManageSettingsの権限が必要です.
+    pub fn set_dispute_window(&mut self, dispute_window: U64) {
+        self.assert_permission(Permission::ManageSettings);
+        log!("set dispute window: {}", dispute_window.0);
+        self.dispute_window = dispute_window.0;
+    }
+
+    // 異議申し立てを解決できるアカウントを追加します. grant_role()でArbiterのロールを付与するのと同じです.
+    pub fn add_arbiter(&mut self, account_id: AccountId) {
+        self.grant_role(account_id, Role::Arbiter);
+    }
+
+    pub fn remove_arbiter(&mut self, account_id: AccountId) {
+        self.revoke_role(account_id, Role::Arbiter);
+    }
+
+    // 修理の報告で責任があるとされたアカウントが, 報告から一定時間内に異議を申し立てます.
+    // 異議申し立てが解決するまで, 預り金は保持されます.
+    pub fn open_dispute(&mut self, index: usize, reason: String) -> u64 {
+        let predecessor_id = env::predecessor_account_id();
+        let report = self.bikes[index]
+            .damage_report
+            .as_ref()
+            .expect("No damage report for the bike");
+        assert_eq!(
+            report.renter_id, predecessor_id,
+            "Only the blamed renter can open a dispute"
+        );
+        assert!(
+            env::block_timestamp() <= report.reported_at + self.dispute_window,
+            "Dispute window has passed"
+        );
+        let inspector_id = report.inspector_id.clone();
+
+        let dispute_id = self.next_dispute_id;
+        let amount = self.dispute_deposit(index, &predecessor_id, dispute_id);
+        self.next_dispute_id += 1;
+        let initial_storage = env::storage_usage();
+        self.disputes.insert(
+            &dispute_id,
+            &Dispute {
+                index,
+                renter_id: predecessor_id.clone(),
+                inspector_id,
+                reason,
+                amount,
+                status: DisputeStatus::Open,
+                opened_at: env::block_timestamp(),
+                resolved_by: None,
+                resolved_at: None,
+            },
+        );
+        emit_event(
+            "dispute_opened",
+            json!({
+                "dispute_id": dispute_id,
+                "index": index,
+                "renter_id": predecessor_id,
+                "amount": U128::from(amount),
+            }),
+        );
+        self.charge_storage(&predecessor_id, initial_storage);
+        dispute_id
+    }
+
+    // 異議申し立てを解決します. ResolveDisputesの権限が必要です.
+    // upholdがtrueの場合は預り金をバイクを使用したアカウントが引き出せるようにし, falseの場合は使用料として受け取ります.
+    pub fn resolve_dispute(&mut self, dispute_id: u64, uphold: bool) {
+        let predecessor_id = env::predecessor_account_id();
+        self.assert_permission(Permission::ResolveDisputes);
+        let mut dispute = self.disputes.get(&dispute_id).expect("Dispute not found");
+        assert_eq!(
+            dispute.status,
+            DisputeStatus::Open,
+            "Dispute is already resolved"
+        );
+
+        self.settle_disputed_deposit(dispute.index, dispute_id, uphold);
+        if !uphold {
+            self.penalize_damage(&dispute.renter_id);
+        }
+        self.settle_damage_report(dispute.index, &dispute.renter_id);
+        dispute.status = if uphold {
+            DisputeStatus::Upheld
+        } else {
+            DisputeStatus::Rejected
+        };
+        dispute.resolved_by = Some(predecessor_id.clone());
+        dispute.resolved_at = Some(env::block_timestamp());
+        emit_event(
+            "dispute_resolved",
+            json!({
+                "dispute_id": dispute_id,
+                "status": dispute.status,
+                "resolved_by": predecessor_id,
+            }),
+        );
+        self.disputes.insert(&dispute_id, &dispute);
+    }
+}
+
+impl Contract {
+    // 点検者による修理の報告を, バイクを最後に使用したアカウントに責任があるものとして記録します.
+    pub(crate) fn report_damage(&mut self, index: usize, inspector_id: AccountId) {
+        let renter_id = match self.bikes[index].last_user.clone() {
+            Some(renter_id) => renter_id,
+            None => return,
+        };
+        emit_event(
+            "damage_reported",
+            json!({
+                "index": index,
+                "inspector_id": inspector_id,
+                "renter_id": renter_id,
+            }),
+        );
+        // 修理の報告はバイクの情報に含まれるため, その大きさのストレージを点検者が負担します.
+        self.clear_damage_report(index);
+        let report = DamageReport {
+            inspector_id,
+            renter_id,
+            reported_at: env::block_timestamp(),
+        };
+        self.charge_storage_bytes(&report.inspector_id, storage_size(&report));
+        self.bikes[index].damage_report = Some(report);
+    }
+
+    // 修理の報告を削除し, 点検者が負担していたストレージを解放します.
+    fn clear_damage_report(&mut self, index: usize) {
+        if let Some(report) = self.bikes[index].damage_report.take() {
+            self.release_storage_bytes(&report.inspector_id, storage_size(&report));
+        }
+    }
+
+    // 修理の報告で責任があるとされ, まだ異議を申し立てられるアカウント
+    pub(crate) fn blamed_renter(&self, index: usize) -> Option<AccountId> {
+        self.bikes[index]
+            .damage_report
+            .as_ref()
+            .filter(|report| env::block_timestamp() <= report.reported_at + self.dispute_window)
+            .map(|report| report.renter_id.clone())
+    }
+
+    // 預り金の受け取りや異議申し立ての解決によって, renter_idに対する修理の報告を精算済みにします.
+    pub(crate) fn settle_damage_report(&mut self, index: usize, renter_id: &AccountId) {
+        if self.bikes[index]
+            .damage_report
+            .as_ref()
+            .is_some_and(|report| report.renter_id == *renter_id)
+        {
+            self.clear_damage_report(index);
+        }
+    }
+
+    // 異議申し立てができる時間を過ぎ, 異議申し立て中でない修理の報告を精算済みにします.
+    pub(crate) fn expire_damage_report(&mut self, index: usize) {
+        let renter_id = match &self.bikes[index].damage_report {
+            Some(report) => report.renter_id.clone(),
+            None => return,
+        };
+        if !self.has_disputed_deposit(index, &renter_id) {
+            self.clear_damage_report(index);
+        }
+    }
+}
+
+// NEP-297の形式でイベントを出力します.
+pub(crate) fn emit_event(event: &str, data: serde_json::Value) {
+    let event = json!({
+        "standard": "bike_share",
+        "version": "1.0.0",
+        "event": event,
+        "data": [data],
+    });
+    env::log_str(&format!("EVENT_JSON:{}", event));
+}
@@ -0,0 +1,304 @@
+use near_sdk::{
+    borsh::{self, BorshDeserialize, BorshSerialize},
+    env,
+    json_types::Base64VecU8,
+    log, near_bindgen,
+    serde::{Deserialize, Serialize},
+    serde_json::json,
+    AccountId, Gas, Promise, PromiseResult,
+};
+
+use crate::*;
+
+// 提案で呼び出せるメソッド. 料金や割引, 報酬の設定, プロモーションの作成, 預り金と資金の引き出し,
+// ロールとガバナンスの変更, アップグレードです.
+const GOVERNED_METHODS: &[&str] = &[
+    "set_near_price",
+    "add_accepted_token",
+    "remove_accepted_token",
+    "set_marketplace_config",
+    "set_pass_config",
+    "set_security_deposit_bps",
+    "set_loyalty_config",
+    "set_referral_bonus",
+    "set_rebalancing_config",
+    "create_promo_campaign",
+    "claim_deposit",
+    "set_revenue_share",
+    "withdraw_revenue",
+    "withdraw_near_revenue",
+    "withdraw_token_revenue",
+    "allocate_revenue",
+    "allocate_unaccounted_ft",
+    "grant_role",
+    "revoke_role",
+    "set_council",
+    "set_dao",
+    "upgrade",
+];
+const GAS_FOR_PROPOSAL_EXECUTION: Gas = Gas(100_000_000_000_000);
+const GAS_FOR_MIGRATION: Gas = Gas(30_000_000_000_000);
+
+// 提案の状態
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum ProposalStatus {
+    Open,      // 承認を受け付けています.
+    Executing, // 実行中. 失敗した場合はOpenに戻ります.
+    Executed,
+}
+
+// 評議会のメンバーによる, このコントラクトのメソッドの呼び出しの提案
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct Proposal {
+    proposer_id: AccountId,
+    method_name: String,
+    args: Vec<u8>,
+    description: String,
+    // 提案を承認した評議会のメンバー. 提案者を含みます.
+    approvals: Vec<AccountId>,
+    status: ProposalStatus,
+}
+
+// get_proposal()の返り値
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ProposalView {
+    pub proposer_id: AccountId,
+    pub method_name: String,
+    pub args: Base64VecU8,
+    pub description: String,
+    pub approvals: Vec<AccountId>,
+    pub status: ProposalStatus,
+}
+
+#[near_bindgen]
+impl Contract {
+    pub fn council(&self) -> Vec<AccountId> {
+        self.council.to_vec()
+    }
+
+    // 提案の実行に必要な承認の数. 0の場合は評議会による承認を使用しません.
+    pub fn approval_threshold(&self) -> u32 {
+        self.approval_threshold
+    }
+
+    // 管理を委ねたDAOのコントラクト
+    pub fn dao_id(&self) -> Option<AccountId> {
+        self.dao_id.clone()
+    }
+
+    pub fn get_proposal(&self, proposal_id: u64) -> Option<ProposalView> {
+        self.proposals
+            .get(&proposal_id)
+            .map(|proposal| ProposalView {
+                proposer_id: proposal.proposer_id,
+                method_name: proposal.method_name,
+                args: Base64VecU8::from(proposal.args),
+                description: proposal.description,
+                approvals: proposal.approvals,
+                status: proposal.status,
+            })
+    }
+
+    // 評議会のメンバーと承認の数を設定します. 承認の数が1以上の場合, GOVERNED_METHODSは提案の実行でのみ呼び出せます.
+    // ManageRolesの権限が必要で, 評議会を設定した後は提案で変更します.
+    pub fn set_council(&mut self, members: Vec<AccountId>, approval_threshold: u32) {
+        self.assert_governed(Permission::ManageRoles);
+        // 重複したメンバーを除いた人数で承認の数を確認します.
+        self.council.clear();
+        for member in members.iter() {
+            self.council.insert(member);
+        }
+        assert!(
+            approval_threshold as u64 <= self.council.len(),
+            "Approval threshold exceeds the number of council members"
+        );
+        log!(
+            "set council: {} members, threshold {}",
+            self.council.len(),
+            approval_threshold
+        );
+        self.approval_threshold = approval_threshold;
+    }
+
+    // 管理をDAOのコントラクトに委ねます. DAOにOwnerのロールを付与し, 他のアカウントのOwnerのロールを取り消します.
+    // DAOは提案を経ずにGOVERNED_METHODSを呼び出せます.
+    pub fn set_dao(&mut self, dao_id: AccountId) {
+        self.assert_governed(Permission::ManageRoles);
+        self.add_role(&dao_id, Role::Owner);
+        for owner_id in self.accounts_with_role(Role::Owner) {
+            if owner_id != dao_id {
+                self.remove_role(&owner_id, Role::Owner);
+            }
+        }
+        emit_event("ownership_transferred", json!({ "dao_id": dao_id }));
+        self.dao_id = Some(dao_id);
+    }
+
+    // コントラクトのコードを置き換え, 続けて新しいコードのmigrate()で状態を移行します.
+    pub fn upgrade(&mut self, code: Base64VecU8) -> Promise {
+        self.assert_governed(Permission::Upgrade);
+        log!("upgrade contract");
+        Promise::new(env::current_account_id())
+            .deploy_contract(code.into())
+            .function_call("migrate".to_string(), Vec::new(), 0, GAS_FOR_MIGRATION)
+    }
+
+    // GOVERNED_METHODSの呼び出しを提案します. 評議会のメンバーのみ呼び出せます.
+    pub fn propose(&mut self, method_name: String, args: Base64VecU8, description: String) -> u64 {
+        let predecessor_id = env::predecessor_account_id();
+        let initial_storage = env::storage_usage();
+        assert!(self.approval_threshold > 0, "Council is not configured");
+        self.assert_council_member(&predecessor_id);
+        assert!(
+            GOVERNED_METHODS.contains(&method_name.as_str()),
+            "Method cannot be proposed"
+        );
+        let proposal_id = self.next_proposal_id;
+        self.next_proposal_id += 1;
+        emit_event(
+            "proposal_created",
+            json!({
+                "proposal_id": proposal_id,
+                "proposer_id": predecessor_id,
+                "method_name": method_name,
+            }),
+        );
+        self.proposals.insert(
+            &proposal_id,
+            &Proposal {
+                proposer_id: predecessor_id.clone(),
+                method_name,
+                args: args.into(),
+                description,
+                approvals: vec![predecessor_id.clone()],
+                status: ProposalStatus::Open,
+            },
+        );
+        self.charge_storage(&predecessor_id, initial_storage);
+        proposal_id
+    }
+
+    // 提案を承認します. 評議会のメンバーのみ呼び出せます.
+    pub fn approve(&mut self, proposal_id: u64) {
+        let predecessor_id = env::predecessor_account_id();
+        let initial_storage = env::storage_usage();
+        self.assert_council_member(&predecessor_id);
+        let mut proposal = self
+            .proposals
+            .get(&proposal_id)
+            .expect("Proposal not found");
+        assert_eq!(
+            proposal.status,
+            ProposalStatus::Open,
+            "Proposal is not open"
+        );
+        assert!(
+            !proposal.approvals.contains(&predecessor_id),
+            "Proposal is already approved by the account"
+        );
+        proposal.approvals.push(predecessor_id.clone());
+        emit_event(
+            "proposal_approved",
+            json!({
+                "proposal_id": proposal_id,
+                "account_id": predecessor_id,
+            }),
+        );
+        self.proposals.insert(&proposal_id, &proposal);
+        self.charge_storage(&predecessor_id, initial_storage);
+    }
+
+    // 承認の数が足りた提案を実行します. 現在の評議会のメンバーによる承認のみを数えます.
+    pub fn execute_proposal(&mut self, proposal_id: u64) -> Promise {
+        let mut proposal = self
+            .proposals
+            .get(&proposal_id)
+            .expect("Proposal not found");
+        assert_eq!(
+            proposal.status,
+            ProposalStatus::Open,
+            "Proposal is not open"
+        );
+        let approvals = proposal
+            .approvals
+            .iter()
+            .filter(|account_id| self.council.contains(account_id))
+            .count();
+        assert!(
+            self.approval_threshold > 0 && approvals >= self.approval_threshold as usize,
+            "Not enough approvals"
+        );
+        log!(
+            "execute proposal {}: {}",
+            proposal_id,
+            &proposal.method_name
+        );
+        proposal.status = ProposalStatus::Executing;
+        self.proposals.insert(&proposal_id, &proposal);
+
+        // このコントラクト自身からの呼び出しとして, 提案されたメソッドを実行します.
+        Promise::new(env::current_account_id())
+            .function_call(
+                proposal.method_name,
+                proposal.args,
+                0,
+                GAS_FOR_PROPOSAL_EXECUTION,
+            )
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_CALLBACK)
+                    .callback_execute_proposal(proposal_id),
+            )
+    }
+
+    // callback
+    // 実行に失敗した提案は, 再度実行できるようOpenに戻します.
+    #[private]
+    pub fn callback_execute_proposal(&mut self, proposal_id: u64) -> bool {
+        assert_eq!(env::promise_results_count(), 1, "This is a callback method");
+        let mut proposal = self.proposals.get(&proposal_id).unwrap();
+        let executed = match env::promise_result(0) {
+            PromiseResult::NotReady => unreachable!(),
+            PromiseResult::Failed => {
+                log!("Fail to execute proposal {}", proposal_id);
+                proposal.status = ProposalStatus::Open;
+                false
+            }
+            PromiseResult::Successful(_) => {
+                proposal.status = ProposalStatus::Executed;
+                emit_event("proposal_executed", json!({ "proposal_id": proposal_id }));
+                true
+            }
+        };
+        self.proposals.insert(&proposal_id, &proposal);
+        executed
+    }
+}
+
+impl Contract {
+    // GOVERNED_METHODSの呼び出しを確認します.
+    // 提案の実行(このコントラクト自身からの呼び出し)とDAOは常に呼び出せます.
+    // 評議会が設定されている場合はそれ以外のアカウントは呼び出せず, 設定されていない場合は権限を確認します.
+    pub(crate) fn assert_governed(&self, permission: Permission) {
+        let predecessor_id = env::predecessor_account_id();
+        if predecessor_id == env::current_account_id()
+            || self.dao_id.as_ref() == Some(&predecessor_id)
+        {
+            return;
+        }
+        assert!(
+            self.approval_threshold == 0,
+            "Requires an approved proposal"
+        );
+        self.assert_permission(permission);
+    }
+
+    fn assert_council_member(&self, account_id: &AccountId) {
+        assert!(
+            self.council.contains(account_id),
+            "Only council members can call this method"
+        );
+    }
+}
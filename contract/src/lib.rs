@@ -1,52 +1,428 @@
 use near_sdk::{
     borsh::{self, BorshDeserialize, BorshSerialize},
+    collections::{LookupMap, LookupSet, UnorderedMap, UnorderedSet},
     env, ext_contract,
-    json_types::{self, U128},
-    log, near_bindgen, AccountId, Gas, PanicOnDefault, Promise, PromiseOrValue, PromiseResult,
+    json_types::{self, U128, U64},
+    log, near_bindgen,
+    serde::{Deserialize, Serialize},
+    serde_json, AccountId, Balance, BorshStorageKey, CryptoHash, Gas, PanicOnDefault, Promise,
+    PromiseOrValue, PromiseResult,
 };
 
+mod batch;
+mod city;
+mod deposits;
+mod disputes;
+mod governance;
+mod loyalty;
+mod marketplace;
+mod migration;
+mod passes;
+mod promos;
+mod referrals;
+mod reputation;
+mod roles;
+mod stations;
+mod storage;
+mod tokens;
+mod treasury;
+
+pub use crate::batch::*;
+pub use crate::city::*;
+pub use crate::deposits::*;
+pub use crate::disputes::*;
+pub use crate::governance::*;
+pub use crate::loyalty::*;
+pub use crate::marketplace::*;
+pub use crate::migration::*;
+pub use crate::passes::*;
+pub use crate::promos::*;
+pub use crate::referrals::*;
+pub use crate::reputation::*;
+pub use crate::roles::*;
+pub use crate::stations::*;
+pub use crate::storage::*;
+pub use crate::tokens::*;
+pub use crate::treasury::*;
+
 const FT_CONTRACT_ACCOUNT: &str = "my_ft.testnet";
 const AMOUNT_TO_USE_BIKE: u128 = 30;
-const AMOUNT_REWARD_FOR_INSPECTIONS: u128 = 15;
+// 使用料のうち点検の報酬の資金に回す割合の初期値(ベーシスポイント, 10000 = 100%)
+const DEFAULT_REWARD_POOL_SHARE_BPS: u32 = 5000;
+// 点検1回の報酬として支払う, 点検の報酬の資金の割合の初期値(ベーシスポイント)
+const DEFAULT_INSPECTION_REWARD_BPS: u32 = 1000;
+// バイクの所有者が設定できる料金の範囲と, コントラクトが受け取る手数料の初期値
+const DEFAULT_MIN_PRICE: u128 = 1;
+const DEFAULT_MAX_PRICE: u128 = 10 * AMOUNT_TO_USE_BIKE;
+const DEFAULT_PLATFORM_FEE_BPS: u32 = 1000;
+// 所有者ごとと全体で登録できるバイクの台数の上限の初期値
+const DEFAULT_MAX_LISTINGS_PER_OWNER: u32 = 10;
+const DEFAULT_MAX_LISTINGS: u32 = 500;
+// NEARでバイクを使用するのに必要なyoctoNEARの初期値: 0.1NEAR
+const DEFAULT_NEAR_PRICE_TO_USE_BIKE: Balance = 100_000_000_000_000_000_000_000;
+// 定期券の料金の初期値と, 定期券で1日に使用できる回数の初期値
+const DEFAULT_DAY_PASS_PRICE: u128 = 3 * AMOUNT_TO_USE_BIKE;
+const DEFAULT_WEEK_PASS_PRICE: u128 = 15 * AMOUNT_TO_USE_BIKE;
+const DEFAULT_MONTH_PASS_PRICE: u128 = 50 * AMOUNT_TO_USE_BIKE;
+const DEFAULT_PASS_DAILY_RIDE_CAP: u32 = 5;
 const AMOUNT_GIFT_FOR_NEW_USER: u128 = 30;
+// 紹介されたアカウントが初めてバイクの使用を終えた際に, 紹介者に支払う報酬の初期値
+const DEFAULT_REFERRAL_BONUS: u128 = 10;
+// ポイントの付与と交換, 会員ランクの条件と割引(%)の初期値
+const DEFAULT_POINTS_PER_MINUTE: u128 = 1;
+const DEFAULT_MAX_POINTS_PER_RIDE: u128 = 300;
+const DEFAULT_FREE_RIDE_POINTS: u128 = 300;
+const DEFAULT_BRONZE_POINTS: u128 = 100;
+const DEFAULT_SILVER_POINTS: u128 = 500;
+const DEFAULT_GOLD_POINTS: u128 = 2000;
+const DEFAULT_BRONZE_DISCOUNT: u32 = 5;
+const DEFAULT_SILVER_DISCOUNT: u32 = 10;
+const DEFAULT_GOLD_DISCOUNT: u32 = 20;
+// 使用料のうち預り金として受け取る割合の初期値(ベーシスポイント). 初期値では預り金を受け取りません.
+const DEFAULT_SECURITY_DEPOSIT_BPS: u32 = 0;
+// 修理の報告から異議申し立てができるまでの時間の初期値: 3日(ナノ秒)
+const DEFAULT_DISPUTE_WINDOW: u64 = 3 * 24 * 60 * 60 * 1_000_000_000;
+// 評価の増減と下限, 返却までの期限(24時間)の初期値
+const DEFAULT_ON_TIME_REWARD: i32 = 1;
+const DEFAULT_OVERDUE_PENALTY: i32 = 5;
+const DEFAULT_DAMAGE_PENALTY: i32 = 20;
+const DEFAULT_MIN_REPUTATION: i32 = -50;
+const DEFAULT_MAX_RIDE_DURATION: u64 = 24 * 60 * 60 * 1_000_000_000;
+const GAS_FOR_CALLBACK: Gas = Gas(3_000_000_000_000);
+// 点検の間隔の初期値: 7日(ナノ秒)もしくは10回の使用
+const DEFAULT_INSPECTION_INTERVAL: u64 = 7 * 24 * 60 * 60 * 1_000_000_000;
+const DEFAULT_RIDES_PER_INSPECTION: u32 = 10;
+// 点検に最低限かける時間の初期値: 10分(ナノ秒)
+const DEFAULT_MIN_INSPECTION_DURATION: u64 = 10 * 60 * 1_000_000_000;
+// 点検者が次の点検を始めるまでの待機時間の初期値: 1時間(ナノ秒)
+const DEFAULT_INSPECTOR_COOLDOWN: u64 = 60 * 60 * 1_000_000_000;
+// new()で登録するステーションと, 置けるバイクの台数の初期値
+const DEFAULT_STATION_ID: &str = "default";
+const DEFAULT_STATION_CAPACITY: u32 = 100;
 
+#[allow(dead_code)]
 #[ext_contract(ext_ft)]
 trait FungibleToken {
     fn ft_transfer(&mut self, receiver_id: String, amount: String, memo: Option<String>);
+    fn ft_balance_of(&self, account_id: AccountId) -> U128;
 }
 
 // Bikeの状態をenumで管理します.
 // enumでの管理: 状態遷移が明瞭, かつ必ずこの内のどれかの状態であるという保証ができる利点があると理解
 #[derive(BorshDeserialize, BorshSerialize)]
 enum Bike {
-    Available,             // 使用可能
-    InUse(AccountId),      // AccountIdによって使用中
-    Inspection(AccountId), // AccountIdによって点検中
+    Available,              // 使用可能
+    InUse(AccountId),       // AccountIdによって使用中
+    Inspection(AccountId),  // AccountIdによって点検中
+    Maintenance(AccountId), // AccountIdの報告によって修理中
+    Delisted,               // 所有者によって登録解除
+}
+
+// バイク1台ごとの情報.
+// 状態(Bike)に加えて, 状態が変わった時刻や修理にかかった累計時間を記録します.
+#[derive(BorshDeserialize, BorshSerialize)]
+struct BikeInfo {
+    state: Bike,
+    // バイクの所有者. コントラクトが所有するバイクの場合はNone
+    owner_id: Option<AccountId>,
+    // 所有者が設定したバイクを使用するのに必要なft. 所有者がいるバイクのみ使用します.
+    price: u128,
+    // 最後に状態が変わった時刻(env::block_timestamp(), ナノ秒)
+    state_updated_at: u64,
+    // 修理中だった累計時間(ナノ秒)
+    maintenance_time: u64,
+    // 最後に点検が完了した時刻. 一度も点検されていなければNone
+    last_inspected_at: Option<u64>,
+    // 最後の点検以降に使用された回数
+    rides_since_inspection: u32,
+    // 最後にバイクを使用したアカウント
+    last_user: Option<AccountId>,
+    // 点検中のバイクの報酬として取り分けたFT. 報酬の送信中はNone
+    pending_reward: Option<u128>,
+    // 使用中のバイクの使用料の支払い
+    payment: Option<Payment>,
+    // 最後の点検者による修理の報告. 報告されていなければNone
+    damage_report: Option<DamageReport>,
+    // バイクが置かれている, もしくは最後に返却されたステーション. ステーションに置かれていなければNone
+    station_id: Option<String>,
+    // 管理者がadd_bikes()で追加したバイクの識別情報
+    metadata: Option<BikeMetadata>,
+}
+
+impl BikeInfo {
+    fn new(owner_id: Option<AccountId>, price: u128) -> Self {
+        Self {
+            state: Bike::Available,
+            owner_id,
+            price,
+            state_updated_at: env::block_timestamp(),
+            maintenance_time: 0,
+            last_inspected_at: None,
+            rides_since_inspection: 0,
+            last_user: None,
+            pending_reward: None,
+            payment: None,
+            damage_report: None,
+            station_id: None,
+            metadata: None,
+        }
+    }
+
+    // 点検を受けたことがない, もしくは前回の点検から時間か使用回数が規定を超えていれば点検が必要です.
+    fn is_due_for_inspection(&self, interval: u64, rides_per_inspection: u32) -> bool {
+        match self.last_inspected_at {
+            None => true,
+            Some(inspected_at) => {
+                env::block_timestamp() - inspected_at >= interval
+                    || self.rides_since_inspection >= rides_per_inspection
+            }
+        }
+    }
+}
+
+// 機能ごとの一時停止の状態.
+// 不具合や攻撃が見つかった際に, 管理者が該当する機能を停止するために使用します.
+// バイクの返却は, 使用者が使用を終えられるよう停止中も可能です.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Default)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PauseState {
+    // バイクの使用(ft_on_transfer)を停止. 停止中に受信したFTは返金されます.
+    pub rentals: bool,
+    // 点検の開始を停止
+    pub inspections: bool,
+    // コントラクトからのFTの送信(点検の報酬, 新規ユーザへの送信)を停止
+    pub payouts: bool,
+}
+
+// コレクションがストレージ上で使用するキーのプレフィックス
+#[derive(BorshStorageKey, BorshSerialize)]
+enum StorageKey {
+    InspectorLastInspections,
+    OwnerEarnings,
+    AcceptedTokens,
+    TokenRevenue,
+    Passes,
+    PromoCampaigns,
+    PromoCodes,
+    PromoUses,
+    Referrals,
+    Referrers,
+    LoyaltyAccounts,
+    DepositBalances,
+    Disputes,
+    Stations,
+    StationLocations,
+    Reputations,
+    BannedAccounts,
+    StorageAccounts,
+    Roles,
+    Council,
+    Proposals,
+    GiftedAccounts,
+    HeldDeposits,
+    UnpaidInspectionRewards,
 }
 
 // コントラクトの定義
 #[near_bindgen]
 #[derive(BorshDeserialize, BorshSerialize, PanicOnDefault)]
 pub struct Contract {
-    bikes: Vec<Bike>,
+    // アカウントごとのロール
+    roles: UnorderedMap<AccountId, Vec<Role>>,
+    bikes: Vec<BikeInfo>,
+    // 点検が必要になるまでの時間(ナノ秒)
+    inspection_interval: u64,
+    // 点検が必要になるまでの使用回数
+    rides_per_inspection: u32,
+    // 点検を始めてから返却できるまでの最短時間(ナノ秒)
+    min_inspection_duration: u64,
+    // 点検者が続けて点検を始められるまでの待機時間(ナノ秒)
+    inspector_cooldown: u64,
+    // 点検者ごとの最後に点検を始めた時刻
+    inspector_last_inspections: LookupMap<AccountId, u64>,
+    // trueの場合, 登録されていないアカウントも点検できます.
+    open_inspection: bool,
+    pause_state: PauseState,
+    treasury: Treasury,
+    revenue_share: RevenueShare,
+    marketplace_config: MarketplaceConfig,
+    // バイクの所有者ごとの受け取れる使用料
+    owner_earnings: LookupMap<AccountId, u128>,
+    // 使用料の支払いに使用できるトークンと, コントラクトが所有するバイクの使用料
+    accepted_tokens: UnorderedMap<AccountId, u128>,
+    // FT_CONTRACT_ACCOUNT以外のトークンで受け取った使用料
+    token_revenue: LookupMap<AccountId, u128>,
+    // NEARでバイクを使用するのに必要なyoctoNEAR
+    near_price: Balance,
+    // NEARで受け取った使用料(yoctoNEAR)
+    near_revenue: Balance,
+    pass_config: PassConfig,
+    // アカウントごとの定期券
+    passes: LookupMap<AccountId, Pass>,
+    promo_campaigns: UnorderedMap<String, PromoCampaign>,
+    // プロモーションコードのハッシュと, プロモーション
+    promo_codes: LookupMap<CryptoHash, String>,
+    // プロモーションとアカウントごとのプロモーションコードを使用した回数
+    promo_uses: LookupMap<(String, AccountId), u32>,
+    referral_bonus: u128,
+    // 紹介されたアカウントごとの紹介
+    referrals: LookupMap<AccountId, Referral>,
+    // 紹介者ごとの紹介の記録
+    referrers: LookupMap<AccountId, ReferrerInfo>,
+    loyalty_config: LoyaltyConfig,
+    // アカウントごとのポイント
+    loyalty_accounts: LookupMap<AccountId, LoyaltyAccount>,
+    security_deposit_bps: u32,
+    // 点検が完了し, アカウントが引き出せる預り金
+    deposit_balances: LookupMap<(AccountId, Currency), u128>,
+    dispute_window: u64,
+    disputes: LookupMap<u64, Dispute>,
+    next_dispute_id: u64,
+    stations: UnorderedMap<String, Station>,
+    // ステーションの場所と, ステーション
+    station_locations: LookupMap<Location, String>,
+    rebalancing_config: RebalancingConfig,
+    reputation_config: ReputationConfig,
+    // アカウントごとの評価
+    reputations: LookupMap<AccountId, i32>,
+    // 利用禁止のアカウント
+    banned_accounts: UnorderedSet<AccountId>,
+    // NEP-145: アカウントごとのストレージの預り金
+    storage_accounts: LookupMap<AccountId, StorageAccount>,
+    // アカウントの登録に必要なストレージ(バイト)
+    account_storage_usage: u64,
+    // 提案を承認する評議会のメンバー
+    council: UnorderedSet<AccountId>,
+    approval_threshold: u32,
+    proposals: LookupMap<u64, Proposal>,
+    next_proposal_id: u64,
+    dao_id: Option<AccountId>,
+    // 新規ユーザへの送信を受け取ったアカウント
+    gifted_accounts: LookupSet<AccountId>,
+    // バイクごとの, 次の点検まで保持する, バイクを使用したアカウントの預り金
+    held_deposits: LookupMap<u64, Vec<Deposit>>,
+    // 支払いの停止中に点検を終えた点検者ごとの, 取り分けたまま受け取っていない報酬
+    unpaid_inspection_rewards: LookupMap<AccountId, u128>,
 }
 
 // Implement the contract structure
 // panicやassertの使用について: 処理ができない場合はなるべく早くプログラムを停止させることでトランザクションにかかる余分なガス代を削減するため.
 #[near_bindgen]
 impl Contract {
+    // 初期のステーションを登録し, num_of_bikes台のバイクを置いて初期化します.
     #[init]
     pub fn new(num_of_bikes: usize) -> Self {
+        let mut this = Self::new_without_stations(num_of_bikes);
+        let capacity = DEFAULT_STATION_CAPACITY.max(num_of_bikes as u32);
+        this.add_station(
+            DEFAULT_STATION_ID.to_string(),
+            Location { lat: 0, lon: 0 },
+            capacity,
+        );
+        this.place_bikes();
+        this
+    }
+
+    // ステーションを登録せずに初期化します. バイクはステーションに置かれていない状態です.
+    fn new_without_stations(num_of_bikes: usize) -> Self {
         log!("initialize Contract with {} bikes", num_of_bikes);
-        Self {
+        let mut this = Self {
+            roles: UnorderedMap::new(StorageKey::Roles),
             bikes: {
                 let mut bikes = Vec::new();
                 for _i in 0..num_of_bikes {
-                    bikes.push(Bike::Available);
+                    bikes.push(BikeInfo::new(None, 0));
                 }
                 bikes
             },
-        }
+            inspection_interval: DEFAULT_INSPECTION_INTERVAL,
+            rides_per_inspection: DEFAULT_RIDES_PER_INSPECTION,
+            min_inspection_duration: DEFAULT_MIN_INSPECTION_DURATION,
+            inspector_cooldown: DEFAULT_INSPECTOR_COOLDOWN,
+            inspector_last_inspections: LookupMap::new(StorageKey::InspectorLastInspections),
+            open_inspection: true,
+            pause_state: PauseState::default(),
+            treasury: Treasury::default(),
+            revenue_share: RevenueShare {
+                reward_pool_bps: DEFAULT_REWARD_POOL_SHARE_BPS,
+                inspection_reward_bps: DEFAULT_INSPECTION_REWARD_BPS,
+            },
+            marketplace_config: MarketplaceConfig {
+                min_price: U128::from(DEFAULT_MIN_PRICE),
+                max_price: U128::from(DEFAULT_MAX_PRICE),
+                platform_fee_bps: DEFAULT_PLATFORM_FEE_BPS,
+                max_listings_per_owner: DEFAULT_MAX_LISTINGS_PER_OWNER,
+                max_listings: DEFAULT_MAX_LISTINGS,
+            },
+            owner_earnings: LookupMap::new(StorageKey::OwnerEarnings),
+            accepted_tokens: {
+                let mut accepted_tokens = UnorderedMap::new(StorageKey::AcceptedTokens);
+                accepted_tokens.insert(&base_token_id(), &AMOUNT_TO_USE_BIKE);
+                accepted_tokens
+            },
+            token_revenue: LookupMap::new(StorageKey::TokenRevenue),
+            near_price: DEFAULT_NEAR_PRICE_TO_USE_BIKE,
+            near_revenue: 0,
+            pass_config: PassConfig {
+                day_price: U128::from(DEFAULT_DAY_PASS_PRICE),
+                week_price: U128::from(DEFAULT_WEEK_PASS_PRICE),
+                month_price: U128::from(DEFAULT_MONTH_PASS_PRICE),
+                daily_ride_cap: DEFAULT_PASS_DAILY_RIDE_CAP,
+            },
+            passes: LookupMap::new(StorageKey::Passes),
+            promo_campaigns: UnorderedMap::new(StorageKey::PromoCampaigns),
+            promo_codes: LookupMap::new(StorageKey::PromoCodes),
+            promo_uses: LookupMap::new(StorageKey::PromoUses),
+            referral_bonus: DEFAULT_REFERRAL_BONUS,
+            referrals: LookupMap::new(StorageKey::Referrals),
+            referrers: LookupMap::new(StorageKey::Referrers),
+            loyalty_config: LoyaltyConfig {
+                points_per_minute: U128::from(DEFAULT_POINTS_PER_MINUTE),
+                max_points_per_ride: U128::from(DEFAULT_MAX_POINTS_PER_RIDE),
+                free_ride_points: U128::from(DEFAULT_FREE_RIDE_POINTS),
+                bronze_points: U128::from(DEFAULT_BRONZE_POINTS),
+                silver_points: U128::from(DEFAULT_SILVER_POINTS),
+                gold_points: U128::from(DEFAULT_GOLD_POINTS),
+                bronze_discount: DEFAULT_BRONZE_DISCOUNT,
+                silver_discount: DEFAULT_SILVER_DISCOUNT,
+                gold_discount: DEFAULT_GOLD_DISCOUNT,
+            },
+            loyalty_accounts: LookupMap::new(StorageKey::LoyaltyAccounts),
+            security_deposit_bps: DEFAULT_SECURITY_DEPOSIT_BPS,
+            deposit_balances: LookupMap::new(StorageKey::DepositBalances),
+            dispute_window: DEFAULT_DISPUTE_WINDOW,
+            disputes: LookupMap::new(StorageKey::Disputes),
+            next_dispute_id: 0,
+            stations: UnorderedMap::new(StorageKey::Stations),
+            station_locations: LookupMap::new(StorageKey::StationLocations),
+            rebalancing_config: RebalancingConfig {
+                adjustment: RebalancingAdjustment::None,
+                relocation_reward: U128::from(0),
+            },
+            reputation_config: ReputationConfig {
+                on_time_reward: DEFAULT_ON_TIME_REWARD,
+                overdue_penalty: DEFAULT_OVERDUE_PENALTY,
+                damage_penalty: DEFAULT_DAMAGE_PENALTY,
+                min_score: DEFAULT_MIN_REPUTATION,
+                max_ride_duration: U64::from(DEFAULT_MAX_RIDE_DURATION),
+            },
+            reputations: LookupMap::new(StorageKey::Reputations),
+            banned_accounts: UnorderedSet::new(StorageKey::BannedAccounts),
+            storage_accounts: LookupMap::new(StorageKey::StorageAccounts),
+            account_storage_usage: 0,
+            council: UnorderedSet::new(StorageKey::Council),
+            approval_threshold: 0,
+            proposals: LookupMap::new(StorageKey::Proposals),
+            next_proposal_id: 0,
+            dao_id: None,
+            gifted_accounts: LookupSet::new(StorageKey::GiftedAccounts),
+            held_deposits: LookupMap::new(StorageKey::HeldDeposits),
+            unpaid_inspection_rewards: LookupMap::new(StorageKey::UnpaidInspectionRewards),
+        };
+        // コントラクトを初期化したアカウントをOwnerとします.
+        this.add_role(&env::predecessor_account_id(), Role::Owner);
+        this.measure_account_storage_usage();
+        this
     }
 
     pub fn num_of_bikes(&self) -> usize {
@@ -55,58 +431,256 @@ impl Contract {
 
     // viewメソッドにするためには&selfを明記します.
     pub fn amount_to_use_bike(&self) -> U128 {
-        json_types::U128::from(AMOUNT_TO_USE_BIKE)
+        json_types::U128::from(self.accepted_tokens.get(&base_token_id()).unwrap())
     }
 
+    // 今点検を始めた場合に支払われる報酬を返します.
     pub fn amount_reward_for_inspections(&self) -> U128 {
-        json_types::U128::from(AMOUNT_REWARD_FOR_INSPECTIONS)
+        json_types::U128::from(self.inspection_reward())
     }
 
     pub fn is_available(&self, index: usize) -> bool {
-        match self.bikes[index] {
-            Bike::Available => true,
-            _ => false,
-        }
+        matches!(self.bikes[index].state, Bike::Available)
+    }
+
+    pub fn is_in_maintenance(&self, index: usize) -> bool {
+        matches!(self.bikes[index].state, Bike::Maintenance(_))
     }
 
     pub fn who_is_using(&self, index: usize) -> Option<AccountId> {
-        match &self.bikes[index] {
+        match &self.bikes[index].state {
             Bike::InUse(user_id) => Some(user_id.clone()),
             _ => None,
         }
     }
 
     pub fn who_is_inspecting(&self, index: usize) -> Option<AccountId> {
-        match &self.bikes[index] {
+        match &self.bikes[index].state {
             Bike::Inspection(inspector_id) => Some(inspector_id.clone()),
             _ => None,
         }
     }
 
+    pub fn who_reported_maintenance(&self, index: usize) -> Option<AccountId> {
+        match &self.bikes[index].state {
+            Bike::Maintenance(reporter_id) => Some(reporter_id.clone()),
+            _ => None,
+        }
+    }
+
+    // 修理中だった累計時間(ナノ秒)を返します. 修理中の場合, 現在の修理の経過時間は含みません.
+    pub fn maintenance_time(&self, index: usize) -> U64 {
+        U64::from(self.bikes[index].maintenance_time)
+    }
+
+    pub fn last_inspected_at(&self, index: usize) -> Option<U64> {
+        self.bikes[index].last_inspected_at.map(U64::from)
+    }
+
+    pub fn rides_since_inspection(&self, index: usize) -> u32 {
+        self.bikes[index].rides_since_inspection
+    }
+
+    pub fn inspection_interval(&self) -> U64 {
+        U64::from(self.inspection_interval)
+    }
+
+    pub fn rides_per_inspection(&self) -> u32 {
+        self.rides_per_inspection
+    }
+
+    pub fn is_due_for_inspection(&self, index: usize) -> bool {
+        self.bikes[index].is_due_for_inspection(self.inspection_interval, self.rides_per_inspection)
+    }
+
+    // 点検が必要で, かつ使用可能なバイクのindexを返します.
+    // 点検者が次に点検するバイクを探すために使用します.
+    pub fn bikes_due_for_inspection(&self) -> Vec<usize> {
+        (0..self.bikes.len())
+            .filter(|&i| self.is_available(i) && self.is_due_for_inspection(i))
+            .collect()
+    }
+
+    // 点検の間隔(時間と使用回数)を設定します. ManageSettingsの権限が必要です.
+    pub fn set_inspection_schedule(&mut self, interval: U64, rides_per_inspection: u32) {
+        self.assert_permission(Permission::ManageSettings);
+        log!(
+            "set inspection schedule: interval {}, rides {}",
+            interval.0,
+            rides_per_inspection
+        );
+        self.inspection_interval = interval.0;
+        self.rides_per_inspection = rides_per_inspection;
+    }
+
+    pub fn min_inspection_duration(&self) -> U64 {
+        U64::from(self.min_inspection_duration)
+    }
+
+    pub fn inspector_cooldown(&self) -> U64 {
+        U64::from(self.inspector_cooldown)
+    }
+
+    // 点検者が最後に点検を始めた時刻を返します.
+    pub fn last_inspection_of(&self, inspector_id: AccountId) -> Option<U64> {
+        self.inspector_last_inspections
+            .get(&inspector_id)
+            .map(U64::from)
+    }
+
+    // 点検の最短時間と点検者の待機時間を設定します. ManageSettingsの権限が必要です.
+    // 点検を始めてすぐに返却して報酬を受け取る行為を防ぐために使用します.
+    pub fn set_inspection_rules(&mut self, min_inspection_duration: U64, inspector_cooldown: U64) {
+        self.assert_permission(Permission::ManageSettings);
+        log!(
+            "set inspection rules: min duration {}, cooldown {}",
+            min_inspection_duration.0,
+            inspector_cooldown.0
+        );
+        self.min_inspection_duration = min_inspection_duration.0;
+        self.inspector_cooldown = inspector_cooldown.0;
+    }
+
+    pub fn inspectors(&self) -> Vec<AccountId> {
+        self.accounts_with_role(Role::Inspector)
+    }
+
+    pub fn is_inspector(&self, account_id: AccountId) -> bool {
+        self.has_role(account_id, Role::Inspector)
+    }
+
+    pub fn is_open_inspection(&self) -> bool {
+        self.open_inspection
+    }
+
+    // 点検を許可するアカウントを追加します. grant_role()でInspectorのロールを付与するのと同じです.
+    pub fn add_inspector(&mut self, account_id: AccountId) {
+        self.grant_role(account_id, Role::Inspector);
+    }
+
+    pub fn remove_inspector(&mut self, account_id: AccountId) {
+        self.revoke_role(account_id, Role::Inspector);
+    }
+
+    // falseを設定すると, 登録されたアカウントのみ点検できるようになります. ManageSettingsの権限が必要です.
+    pub fn set_open_inspection(&mut self, open_inspection: bool) {
+        self.assert_permission(Permission::ManageSettings);
+        log!("set open inspection: {}", open_inspection);
+        self.open_inspection = open_inspection;
+    }
+
+    pub fn pause_state(&self) -> PauseState {
+        self.pause_state.clone()
+    }
+
+    // 機能ごとの一時停止の状態を設定します. Pauseの権限が必要です.
+    pub fn set_pause_state(&mut self, pause_state: PauseState) {
+        self.assert_permission(Permission::Pause);
+        log!(
+            "set pause state: rentals {}, inspections {}, payouts {}",
+            pause_state.rentals,
+            pause_state.inspections,
+            pause_state.payouts
+        );
+        self.pause_state = pause_state;
+    }
+
+    // 緊急停止: 全ての機能を一時停止します. Pauseの権限が必要です.
+    pub fn emergency_stop(&mut self) {
+        self.set_pause_state(PauseState {
+            rentals: true,
+            inspections: true,
+            payouts: true,
+        });
+    }
+
+    pub fn mechanics(&self) -> Vec<AccountId> {
+        self.accounts_with_role(Role::Mechanic)
+    }
+
+    // 修理の完了を許可するアカウントを追加します. grant_role()でMechanicのロールを付与するのと同じです.
+    pub fn add_mechanic(&mut self, account_id: AccountId) {
+        self.grant_role(account_id, Role::Mechanic);
+    }
+
+    pub fn remove_mechanic(&mut self, account_id: AccountId) {
+        self.revoke_role(account_id, Role::Mechanic);
+    }
+
     // cross contract callを呼び出し, 新規ユーザへftを送信します.
     // 返り値にPromiseを取ると, 一連のトランザクションの終了までこのメソッドは返さないためクロスコントラクトコール内で起きたエラーやパニックを拾うことができます.
     // 返り値を省略すると関数呼び出しの直後にこのメソッドは返すため, その後の関数が失敗した場合もこのメソッド自体は成功したと見なされます.
-    pub fn transfer_ft_to_new_user(new_user_id: AccountId) -> Promise {
-        Self::cross_contract_call_transfer(
-            new_user_id.to_string(),
-            AMOUNT_GIFT_FOR_NEW_USER.to_string(),
+    // referrer_idを指定した場合, 紹介者として記録し, 新規ユーザが初めてバイクの使用を終えた際に紹介の報酬を支払います.
+    // 新規ユーザ自身のみ呼び出せ, 送信は1つのアカウントにつき1回のみです.
+    pub fn transfer_ft_to_new_user(
+        &mut self,
+        new_user_id: AccountId,
+        referrer_id: Option<AccountId>,
+    ) -> Promise {
+        assert!(
+            !self.gifted_accounts.contains(&new_user_id),
+            "Account has already received the gift"
+        );
+        self.assert_new_user(&new_user_id);
+        if let Some(referrer_id) = referrer_id {
+            self.add_referral(&new_user_id, referrer_id);
+        }
+        let initial_storage = env::storage_usage();
+        self.gifted_accounts.insert(&new_user_id);
+        self.charge_storage(&new_user_id, initial_storage);
+        self.transfer_from_treasury(
+            TreasuryAccount::GiftBudget,
+            new_user_id.clone(),
+            AMOUNT_GIFT_FOR_NEW_USER,
+        )
+        .then(
+            Self::ext(env::current_account_id())
+                .with_static_gas(GAS_FOR_CALLBACK)
+                .callback_transfer_ft_to_new_user(new_user_id),
         )
     }
 
+    // callback
+    // 送信が失敗した場合, 再度受け取れるよう送信の記録を消します.
+    // 直前のcallback_refund_treasury()の返り値(送信が成功したか)を受け取ります.
+    #[private]
+    pub fn callback_transfer_ft_to_new_user(&mut self, new_user_id: AccountId) {
+        assert_eq!(env::promise_results_count(), 1, "This is a callback method");
+        let transferred = match env::promise_result(0) {
+            PromiseResult::NotReady => unreachable!(),
+            PromiseResult::Failed => false,
+            PromiseResult::Successful(value) => {
+                serde_json::from_slice::<bool>(&value).unwrap_or(false)
+            }
+        };
+        if !transferred {
+            log!("Fail to transfer the gift to {}", &new_user_id);
+            let initial_storage = env::storage_usage();
+            self.gifted_accounts.remove(&new_user_id);
+            self.track_storage(&new_user_id, initial_storage);
+        }
+    }
+
     // cross contract call
-    // receiver_idへamount分ftを送信します.
-    fn cross_contract_call_transfer(receiver_id: String, amount: String) -> Promise {
-        let contract_id = FT_CONTRACT_ACCOUNT.parse().unwrap();
+    // receiver_idへamount分, token_idのftを送信します.
+    fn cross_contract_call_transfer(
+        &self,
+        token_id: &AccountId,
+        receiver_id: String,
+        amount: String,
+    ) -> Promise {
+        assert!(!self.pause_state.payouts, "Payouts are paused");
 
         log!(
-            "{} transfer to {}: {} FT",
+            "{} transfer to {}: {} {}",
             env::current_account_id(),
             &receiver_id,
-            &amount
+            &amount,
+            token_id
         );
 
         // cross contract call
-        ext_ft::ext(contract_id)
+        ext_ft::ext(token_id.clone())
             .with_attached_deposit(1)
             .ft_transfer(receiver_id, amount, None)
     }
@@ -118,12 +692,80 @@ impl Contract {
         amount: String,
         msg: String,
     ) -> PromiseOrValue<U128> {
+        self.receive_ft(sender_id, amount, msg)
+    }
+
+    fn receive_ft(
+        &mut self,
+        sender_id: String,
+        amount: String,
+        msg: String,
+    ) -> PromiseOrValue<U128> {
+        // env::predecessor_account_id(): ft_transfer_call()を実行したftコントラクトを取得
+        let token_id = env::predecessor_account_id();
+        assert!(
+            self.accepted_tokens.get(&token_id).is_some(),
+            "Token is not accepted"
+        );
+
+        // 点検の報酬もしくは新規ユーザへの送信のための資金として受け取ります.
+        if let Some(account) = TreasuryAccount::from_funding_msg(&msg) {
+            assert_eq!(
+                token_id,
+                base_token_id(),
+                "Only {} can fund the treasury",
+                FT_CONTRACT_ACCOUNT
+            );
+            log!("{} funds {:?}: {}", sender_id, account, amount);
+            self.treasury
+                .receive(account, amount.parse::<u128>().unwrap());
+            return PromiseOrValue::Value(U128::from(0));
+        }
+
+        // 使用が停止されている場合, 受信したFTを全て返金します.
+        if self.pause_state.rentals {
+            log!("Rentals are paused, refund {} ft", amount);
+            return PromiseOrValue::Value(U128::from(amount.parse::<u128>().unwrap()));
+        }
+
+        // 利用禁止もしくは評価が下限を下回るアカウントの場合, 受信したFTを全て返金します.
+        if !self.is_allowed_to_ride(sender_id.parse().unwrap()) {
+            log!("{} is not allowed to ride, refund {} ft", sender_id, amount);
+            return PromiseOrValue::Value(U128::from(amount.parse::<u128>().unwrap()));
+        }
+
+        // 定期券の購入として受け取ります.
+        if let Some(kind) = PassKind::from_buy_pass_msg(&msg) {
+            assert_eq!(
+                token_id,
+                base_token_id(),
+                "Only {} can buy the pass",
+                FT_CONTRACT_ACCOUNT
+            );
+            self.buy_pass(
+                sender_id.parse().unwrap(),
+                kind,
+                amount.parse::<u128>().unwrap(),
+            );
+            return PromiseOrValue::Value(U128::from(0));
+        }
+
+        // msgは使用するバイクのindex. プロモーションコードを使用する場合は"<index>:<code>"
+        let (index, promo_code) = match msg.split_once(':') {
+            Some((index, code)) => (index, Some(code)),
+            None => (msg.as_str(), None),
+        };
+        let index: usize = index.parse().unwrap();
+
         // バイクを使用するのに必要なftが送信されたかの確認.
+        // 使用料に加えて預り金が必要です.
+        let price = self.rental_price(index, &token_id);
+        let security_deposit = self.rental_deposit(&Currency::Ft(token_id.clone()), price);
         assert_eq!(
             amount,
-            AMOUNT_TO_USE_BIKE.to_string(),
+            (price + security_deposit).to_string(),
             "Require {} ft to use the bike",
-            AMOUNT_TO_USE_BIKE.to_string()
+            price + security_deposit
         );
 
         log!(
@@ -133,20 +775,55 @@ impl Contract {
             msg
         );
 
-        // bikeコントラクトへftを送信したユーザ(ft_transfer_call()を呼び出したユーザ)によってバイクを使用中に変更
-        self.use_bike(msg.parse().unwrap());
-        // 受信したFTは全て受け取るので0を返却.
-        PromiseOrValue::Value(U128::from(0))
+        // プロモーションコードと会員ランクによる割引. 割り引いた分は返金します.
+        // 所有者がいるバイクは所有者が受け取る使用料が減らないよう, 割引を適用しません.
+        let sender_id: AccountId = sender_id.parse().unwrap();
+        let owned = self.bikes[index].owner_id.is_some();
+        let promo_discount = match promo_code {
+            Some(_) if owned => panic!("Promo code cannot be used for a bike owned by an account"),
+            Some(code) => self.redeem_promo_code(code, sender_id.clone(), &token_id, price),
+            None => 0,
+        };
+        let loyalty_discount = if owned {
+            0
+        } else {
+            self.loyalty_discount(&sender_id, price - promo_discount)
+        };
+        let discount = promo_discount + loyalty_discount;
+        let charged = price - discount;
+
+        // bikeコントラクトへftを送信したユーザ(sender_id)によってバイクを使用中に変更
+        self.use_bike(index, sender_id.clone());
+        // 返金や精算に使用するため, 支払いに使用されたトークンを記録します.
+        self.bikes[index].payment = Some(Payment {
+            currency: Currency::Ft(token_id.clone()),
+            amount: charged,
+            price,
+        });
+        self.hold_deposit(
+            index,
+            sender_id,
+            Currency::Ft(token_id.clone()),
+            security_deposit,
+        );
+        // 使用料は返却時にsettle_payment()で精算します.
+        if token_id == base_token_id() {
+            self.treasury.hold_fee(charged);
+        }
+        // 割引を除いたFTを受け取り, 割引分を返却.
+        PromiseOrValue::Value(U128::from(discount))
     }
 
     // バイク 使用可 -> 使用中
-    // ft_on_transferで使用されます.
-    fn use_bike(&mut self, index: usize) {
-        // env::signer_account_id(): ftコントラクトのft_transfer_call()を呼び出しているアカウントを取得
-        let signer_id = env::signer_account_id();
-        log!("{} uses bike", &signer_id);
-        match &self.bikes[index] {
-            Bike::Available => self.bikes[index] = Bike::InUse(signer_id),
+    // 使用料を支払ったアカウント(ft_on_transferではsender_id)をrenter_idとして受け取ります.
+    // 預り金やプロモーション, ポイントを記録したアカウントと同じアカウントが使用者になります.
+    fn use_bike(&mut self, index: usize, renter_id: AccountId) {
+        log!("{} uses bike", &renter_id);
+        // 返却の際に評価やポイントを記録するため, 登録されたアカウントのみ使用できます.
+        self.assert_registered(&renter_id);
+        self.assert_allowed_to_ride(&renter_id);
+        match &self.bikes[index].state {
+            Bike::Available => self.set_state(index, Bike::InUse(renter_id)),
             _ => panic!("Bike is not available"),
         }
     }
@@ -156,22 +833,61 @@ impl Contract {
         // env::predecessor_account_id(): このメソッドを呼び出しているアカウントを取得
         let predecessor_id = env::predecessor_account_id();
         log!("{} inspects bike", &predecessor_id);
-        match &self.bikes[index] {
-            Bike::Available => self.bikes[index] = Bike::Inspection(predecessor_id),
+        match &self.bikes[index].state {
+            Bike::Available => {
+                assert!(!self.pause_state.inspections, "Inspections are paused");
+                self.assert_allowed_to_ride(&predecessor_id);
+                assert!(
+                    self.open_inspection
+                        || self.has_permission(predecessor_id.clone(), Permission::Inspect),
+                    "Only approved inspectors can inspect the bike"
+                );
+                // 点検が必要なバイクのみ点検でき, 報酬を受け取ることができます.
+                assert!(
+                    self.is_due_for_inspection(index),
+                    "Bike is not due for inspection"
+                );
+                // 自分が直前に使用したバイクは点検できません.
+                assert!(
+                    self.bikes[index].last_user.as_ref() != Some(&predecessor_id),
+                    "Last user cannot inspect the bike"
+                );
+                if let Some(last_inspection) = self.inspector_last_inspections.get(&predecessor_id)
+                {
+                    assert!(
+                        env::block_timestamp() - last_inspection >= self.inspector_cooldown,
+                        "Inspector is in cooldown"
+                    );
+                }
+                // 報酬を支払えることを確認し, 点検の報酬を取り分けておきます.
+                let reward = self.inspection_reward();
+                self.treasury.commit_reward(reward);
+                self.bikes[index].pending_reward = Some(reward);
+                let initial_storage = env::storage_usage();
+                self.inspector_last_inspections
+                    .insert(&predecessor_id, &env::block_timestamp());
+                self.set_state(index, Bike::Inspection(predecessor_id.clone()));
+                self.charge_storage(&predecessor_id, initial_storage);
+            }
             _ => panic!("Bike is not available"),
         }
     }
 
     // バイク 使用中or点検中 -> 使用可
-    pub fn return_bike(&mut self, index: usize) {
+    // 返却した場所(登録されたステーション)を記録します.
+    pub fn return_bike(&mut self, index: usize, location: ReturnLocation) {
         // env::predecessor_account_id(): このメソッドを呼び出しているアカウントを取得
         let predecessor_id = env::predecessor_account_id();
-        log!("{} returns bike", &predecessor_id);
-        match &self.bikes[index] {
+        let station_id = self.resolve_station(location);
+        log!("{} returns bike to {}", &predecessor_id, &station_id);
+        self.assert_station_has_capacity(&station_id);
+        let pickup_station_id = self.bikes[index].station_id.clone();
+        match &self.bikes[index].state {
             Bike::Available => panic!("Bike is already available"),
             Bike::InUse(user) => {
                 assert_eq!(user.clone(), predecessor_id, "Fail due to wrong account");
-                self.bikes[index] = Bike::Available
+                self.end_ride(index, &predecessor_id, &station_id);
+                self.set_state(index, Bike::Available)
             }
             Bike::Inspection(inspector) => {
                 assert_eq!(
@@ -179,38 +895,219 @@ impl Contract {
                     predecessor_id,
                     "Fail due to wrong account"
                 );
-                Self::return_inspected_bike(index);
+                assert!(
+                    env::block_timestamp() - self.bikes[index].state_updated_at
+                        >= self.min_inspection_duration,
+                    "Inspection is too short"
+                );
+                self.add_relocation_reward(index, pickup_station_id.as_deref(), &station_id);
+                self.bikes[index].station_id = Some(station_id);
+                self.return_inspected_bike(index);
             }
+            Bike::Maintenance(_) => panic!("Bike is in maintenance"),
+            Bike::Delisted => panic!("Bike is delisted"),
         };
     }
 
+    // バイク 使用中or点検中or使用可 -> 修理中
+    // 呼び出したアカウントによって条件が異なります.
+    //  - 使用者: 自分が使用中のバイクのみ. バイクを置いた場所(location)が必要で, 使用は返却と同様にそこで終了します.
+    //  - 点検者: 自分が点検中のバイクのみ. 点検の報酬は支払われません.
+    //  - ManageFleetの権限を持つアカウント: 使用可能なバイクのみ.
+    pub fn start_maintenance(&mut self, index: usize, location: Option<ReturnLocation>) {
+        let predecessor_id = env::predecessor_account_id();
+        log!("{} puts bike into maintenance", &predecessor_id);
+        match &self.bikes[index].state {
+            Bike::InUse(user) => {
+                assert_eq!(user.clone(), predecessor_id, "Fail due to wrong account");
+                let station_id =
+                    self.resolve_station(location.expect("Location is required to end the ride"));
+                self.assert_station_has_capacity(&station_id);
+                self.end_ride(index, &predecessor_id, &station_id);
+            }
+            Bike::Inspection(inspector) => {
+                assert_eq!(
+                    inspector.clone(),
+                    predecessor_id,
+                    "Fail due to wrong account"
+                );
+                let reward = self.bikes[index]
+                    .pending_reward
+                    .take()
+                    .expect("Reward payout is in progress");
+                self.treasury.release_reward(reward);
+                self.report_damage(index, predecessor_id.clone());
+            }
+            Bike::Available => {
+                assert!(
+                    self.has_permission(predecessor_id.clone(), Permission::ManageFleet),
+                    "Fail due to wrong account"
+                );
+            }
+            Bike::Maintenance(_) => panic!("Bike is already in maintenance"),
+            Bike::Delisted => panic!("Bike is delisted"),
+        }
+        self.set_state(index, Bike::Maintenance(predecessor_id));
+    }
+
+    // バイク 修理中 -> 使用可
+    // Maintainの権限が必要です.
+    pub fn finish_maintenance(&mut self, index: usize) {
+        let predecessor_id = env::predecessor_account_id();
+        self.assert_permission(Permission::Maintain);
+        log!("{} finishes maintenance of bike", &predecessor_id);
+        match &self.bikes[index].state {
+            Bike::Maintenance(_) => {
+                let bike = &mut self.bikes[index];
+                bike.maintenance_time += env::block_timestamp() - bike.state_updated_at;
+                // 修理で整備されたバイクは点検済みとして扱います.
+                bike.last_inspected_at = Some(env::block_timestamp());
+                bike.rides_since_inspection = 0;
+                // 修理で整備されたため, 保持している預り金を引き出せるようにします.
+                self.release_deposits(index);
+                self.set_state(index, Bike::Available);
+            }
+            _ => panic!("Bike is not in maintenance"),
+        }
+    }
+
+    // 点検を完了し, バイクを使用可能にします.
+    fn complete_inspection(&mut self, index: usize) {
+        let bike = &mut self.bikes[index];
+        bike.last_inspected_at = Some(env::block_timestamp());
+        bike.rides_since_inspection = 0;
+        // 点検でバイクに問題がないことが確認されたため, 保持している預り金を引き出せるようにします.
+        self.release_deposits(index);
+        self.set_state(index, Bike::Available)
+    }
+
+    // 使用中のバイクの使用を終了し, 使用料の精算や評価, ポイントなどを記録します. バイクの状態は呼び出し元で変更します.
+    fn end_ride(&mut self, index: usize, renter_id: &AccountId, station_id: &str) {
+        let pickup_station_id = self.bikes[index].station_id.clone();
+        let bike = &mut self.bikes[index];
+        bike.rides_since_inspection += 1;
+        bike.last_user = Some(renter_id.clone());
+        bike.station_id = Some(station_id.to_string());
+        let ride_time = env::block_timestamp() - bike.state_updated_at;
+        let payment = self.settle_payment(index);
+        self.settle_rebalancing(index, renter_id, pickup_station_id.as_deref(), station_id);
+        self.reward_referrer(renter_id);
+        if let Some(payment) = payment {
+            self.add_loyalty_points(renter_id, ride_time, &payment);
+        }
+        self.update_reputation_on_return(renter_id, ride_time);
+    }
+
+    // 取り分けた点検の報酬を, 点検者が後で受け取れるように加えます.
+    fn add_unpaid_inspection_reward(&mut self, account_id: &AccountId, reward: u128) {
+        let initial_storage = env::storage_usage();
+        let unpaid = self.unpaid_inspection_rewards.get(account_id).unwrap_or(0);
+        self.unpaid_inspection_rewards
+            .insert(account_id, &(unpaid + reward));
+        self.track_storage(account_id, initial_storage);
+    }
+
+    // バイクの状態を変更し, 変更した時刻を記録します.
+    fn set_state(&mut self, index: usize, state: Bike) {
+        let bike = &mut self.bikes[index];
+        bike.state = state;
+        bike.state_updated_at = env::block_timestamp();
+    }
+
     // ftコントラクトのft_transferメソッドを呼び出し(cross contract call),
-    // 点検をしてくれたユーザのアカウントへ報酬として15FTを送信します.
-    pub fn return_inspected_bike(index: usize) -> Promise {
+    // 点検をしてくれたユーザのアカウントへ報酬としてFTを送信します.
+    // 点検が必要かの確認を経ずに報酬が支払われないよう, return_bike()からのみ呼び出します.
+    // 支払いの停止中は報酬を送信せずに点検者の受け取っていない報酬に加え, 返却を完了します.
+    // 点検者は停止が解除された後にclaim_inspection_reward()で報酬を受け取ります.
+    fn return_inspected_bike(&mut self, index: usize) {
+        // 点検を始めた際に取り分けた報酬を支払います.
+        // 送信中に重ねて返却された場合に報酬を二重に支払わないよう, 取り分けた報酬を取り出しておきます.
+        let reward = self.bikes[index]
+            .pending_reward
+            .take()
+            .expect("Reward payout is in progress");
+        if self.pause_state.payouts {
+            log!("Payouts are paused, keep the inspection reward");
+            self.add_unpaid_inspection_reward(&env::predecessor_account_id(), reward);
+            self.complete_inspection(index);
+            return;
+        }
+        self.treasury.pay_reward(reward);
         // callback関数としてバイクを返却するcallback_return_bikeメソッドを呼び出します.
-        Self::cross_contract_call_transfer(
+        self.cross_contract_call_transfer(
+            &base_token_id(),
             env::predecessor_account_id().to_string(),
-            AMOUNT_REWARD_FOR_INSPECTIONS.to_string(),
+            reward.to_string(),
+        )
+        .then(
+            Self::ext(env::current_account_id())
+                .with_static_gas(GAS_FOR_CALLBACK)
+                .callback_return_bike(index, U128::from(reward)),
+        );
+    }
+
+    // 支払いの停止中に点検を終えたバイクの報酬をまとめて受け取ります.
+    pub fn claim_inspection_reward(&mut self) -> Promise {
+        let predecessor_id = env::predecessor_account_id();
+        let initial_storage = env::storage_usage();
+        let reward = self
+            .unpaid_inspection_rewards
+            .remove(&predecessor_id)
+            .expect("No unpaid inspection reward for the account");
+        self.track_storage(&predecessor_id, initial_storage);
+        log!("{} claims inspection reward: {}", &predecessor_id, reward);
+        self.treasury.pay_reward(reward);
+        self.cross_contract_call_transfer(
+            &base_token_id(),
+            predecessor_id.to_string(),
+            reward.to_string(),
         )
         .then(
             Self::ext(env::current_account_id())
-                .with_static_gas(Gas(3_000_000_000_000))
-                .callback_return_bike(index),
+                .with_static_gas(GAS_FOR_CALLBACK)
+                .callback_claim_inspection_reward(predecessor_id, U128::from(reward)),
         )
     }
 
+    // callback
+    // 送信が失敗した場合は, 受け取っていない報酬を元に戻します.
+    #[private]
+    pub fn callback_claim_inspection_reward(&mut self, account_id: AccountId, reward: U128) {
+        assert_eq!(env::promise_results_count(), 1, "This is a callback method");
+        match env::promise_result(0) {
+            PromiseResult::NotReady => unreachable!(),
+            PromiseResult::Failed => {
+                log!("Fail cross-contract call");
+                self.treasury.refund_reward(reward.0);
+                self.add_unpaid_inspection_reward(&account_id, reward.0);
+            }
+            PromiseResult::Successful(_) => {}
+        }
+    }
+
+    // 点検者が受け取っていない点検の報酬
+    pub fn unpaid_inspection_reward(&self, account_id: AccountId) -> U128 {
+        U128::from(self.unpaid_inspection_rewards.get(&account_id).unwrap_or(0))
+    }
+
     // callback
     // cross_contract_call_reward_to_inspectorメソッドの実行後に実行するメソッドを定義
     // private: predecessor(このメソッドを呼び出しているアカウント)とcurrent_account(このコントラクトのアカウント)が同じことをチェックするマクロです.
     //          callbackの場合, コントラクトが自身のメソッドを呼び出すことを期待しています.
     #[private]
-    pub fn callback_return_bike(&mut self, index: usize) {
+    pub fn callback_return_bike(&mut self, index: usize, reward: U128) {
         assert_eq!(env::promise_results_count(), 1, "This is a callback method");
         match env::promise_result(0) {
             PromiseResult::NotReady => unreachable!(),
-            PromiseResult::Failed => panic!("Fail cross-contract call"),
+            // 失敗時は差し引いた報酬を元に戻します. バイクは点検中のままです.
+            // (panicすると元に戻した状態も破棄されるため, logのみ出力します)
+            PromiseResult::Failed => {
+                log!("Fail cross-contract call");
+                self.treasury.refund_reward(reward.0);
+                self.bikes[index].pending_reward = Some(reward.0);
+            }
             // 成功時のみBikeを返却(使用可能に変更)
-            PromiseResult::Successful(_) => self.bikes[index] = Bike::Available,
+            PromiseResult::Successful(_) => self.complete_inspection(index),
         }
     }
 }
@@ -219,12 +1116,36 @@ impl Contract {
 #[cfg(all(test, not(target_arch = "wasm32")))]
 mod tests {
     // テスト環境の構築に必要なものをインポート
+    use near_sdk::json_types::Base64VecU8;
     use near_sdk::test_utils::{accounts, VMContextBuilder};
     use near_sdk::testing_env;
 
     // Contractのモジュールをインポート
     use super::*;
 
+    // テスト用のステーション. new()で登録されます.
+    const STATION: &str = DEFAULT_STATION_ID;
+
+    // テスト用のContractを作成
+    // テストで使用するアカウントはストレージの預り金を預けて登録済みとします.
+    fn new_contract(num_of_bikes: usize) -> Contract {
+        let mut contract = Contract::new(num_of_bikes);
+        for i in 0..6 {
+            contract.storage_accounts.insert(
+                &accounts(i),
+                &StorageAccount {
+                    balance: 10u128.pow(24),
+                    used_bytes: 0,
+                },
+            );
+        }
+        contract
+    }
+
+    fn station() -> ReturnLocation {
+        ReturnLocation::Station(STATION.to_string())
+    }
+
     // VMContextBuilderのテンプレートを用意
     // VMContextBuilder: テスト環境(モックされたブロックチェーン)をcontext(テスト材料)をもとに変更できるインターフェース
     fn get_context(predecessor_account_id: AccountId) -> VMContextBuilder {
@@ -236,6 +1157,40 @@ mod tests {
         builder
     }
 
+    // ftコントラクトのft_transfer_call()からft_on_transferが呼び出された状況を再現します.
+    // signer(ft_transfer_call()を呼び出したアカウント)はそのままで, predecessorをftコントラクトに変更します.
+    fn ft_transfer_call(
+        context: &mut VMContextBuilder,
+        contract: &mut Contract,
+        token_id: AccountId,
+        amount: u128,
+        msg: &str,
+    ) -> PromiseOrValue<U128> {
+        let predecessor_id = context.context.predecessor_account_id.clone();
+        let sender_id = context.context.signer_account_id.clone();
+        testing_env!(context.predecessor_account_id(token_id).build());
+        let result =
+            contract.ft_on_transfer(sender_id.to_string(), amount.to_string(), msg.to_string());
+        testing_env!(context.predecessor_account_id(predecessor_id).build());
+        result
+    }
+
+    // 点検の報酬の資金をコントラクトに入金
+    fn fund_reward_pool(context: &mut VMContextBuilder, contract: &mut Contract) {
+        ft_transfer_call(context, contract, base_token_id(), 100, "fund_reward_pool");
+    }
+
+    // cross contract callの結果を受け取るcallbackメソッドのテスト用に, promiseの結果を設定してテスト環境を初期化
+    fn testing_env_with_promise_result(context: &mut VMContextBuilder, result: PromiseResult) {
+        testing_env!(
+            context.build(),
+            near_sdk::VMConfig::test(),
+            near_sdk::RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![result]
+        );
+    }
+
     // newメソッドのテスト
     #[test]
     fn test_new() {
@@ -244,7 +1199,7 @@ mod tests {
         // テスト環境を初期化
         testing_env!(context.build());
         let init_num = 5;
-        let contract = Contract::new(init_num);
+        let contract = new_contract(init_num);
 
         // view関数のみ実行する環境に初期化
         testing_env!(context.is_view(true).build());
@@ -253,9 +1208,12 @@ mod tests {
             assert!(contract.is_available(i))
         }
         assert_eq!(contract.amount_to_use_bike().0, AMOUNT_TO_USE_BIKE);
+        assert_eq!(contract.amount_reward_for_inspections().0, 0);
+        let revenue_share = contract.revenue_share();
+        assert_eq!(revenue_share.reward_pool_bps, DEFAULT_REWARD_POOL_SHARE_BPS);
         assert_eq!(
-            contract.amount_reward_for_inspections().0,
-            AMOUNT_REWARD_FOR_INSPECTIONS
+            revenue_share.inspection_reward_bps,
+            DEFAULT_INSPECTION_REWARD_BPS
         );
     }
 
@@ -264,12 +1222,12 @@ mod tests {
     fn check_using_account() {
         let mut context = get_context(accounts(1));
         testing_env!(context.build());
-        let mut contract = Contract::new(5);
+        let mut contract = new_contract(5);
 
         // チェックに使用するindexを定義
         let test_index = contract.bikes.len() - 1;
         // バイクを使用
-        contract.use_bike(test_index);
+        contract.use_bike(test_index, accounts(1));
 
         testing_env!(context.is_view(true).build());
         // バイクの状態をチェック
@@ -282,12 +1240,29 @@ mod tests {
         }
     }
 
+    // ft_transfer_call()の署名者ではなく, FTを送信したsender_idがバイクの使用者になることを確認
+    #[test]
+    fn use_bike_as_ft_sender() {
+        let mut context = get_context(accounts(3));
+        testing_env!(context.build());
+        let mut contract = new_contract(1);
+
+        testing_env!(context.predecessor_account_id(base_token_id()).build());
+        contract.ft_on_transfer(
+            accounts(1).to_string(),
+            AMOUNT_TO_USE_BIKE.to_string(),
+            "0".to_string(),
+        );
+        assert_eq!(contract.who_is_using(0).unwrap(), accounts(1));
+    }
+
     // inspect_bike(), who_is_inspecting()のテスト
     #[test]
     fn check_inspecting_account() {
         let mut context = get_context(accounts(1));
         testing_env!(context.build());
-        let mut contract = Contract::new(5);
+        let mut contract = new_contract(5);
+        fund_reward_pool(&mut context, &mut contract);
 
         // チェックに使用するindexを定義
         let test_index = contract.bikes.len() - 1;
@@ -311,19 +1286,20 @@ mod tests {
     fn duplicate_use() {
         let context = get_context(accounts(1));
         testing_env!(context.build());
-        let mut contract = Contract::new(5);
+        let mut contract = new_contract(5);
 
-        contract.use_bike(0);
-        contract.use_bike(0);
+        contract.use_bike(0, accounts(1));
+        contract.use_bike(0, accounts(1));
     }
 
     // 重複してバイクを点検->パニックを起こすか確認
     #[test]
     #[should_panic(expected = "Bike is not available")]
     fn duplicate_inspect() {
-        let context = get_context(accounts(1));
+        let mut context = get_context(accounts(1));
         testing_env!(context.build());
-        let mut contract = Contract::new(5);
+        let mut contract = new_contract(5);
+        fund_reward_pool(&mut context, &mut contract);
 
         contract.inspect_bike(0);
         contract.inspect_bike(0);
@@ -335,9 +1311,9 @@ mod tests {
     fn duplicate_return() {
         let context = get_context(accounts(1));
         testing_env!(context.build());
-        let mut contract = Contract::new(5);
+        let mut contract = new_contract(5);
 
-        contract.return_bike(0);
+        contract.return_bike(0, station());
     }
 
     // 別のアカウントが使用中に使用可能に変更->パニックを起こすか確認
@@ -346,13 +1322,2316 @@ mod tests {
     fn return_by_other_account() {
         let mut context = get_context(accounts(1));
         testing_env!(context.build());
-        let mut contract = Contract::new(5);
+        let mut contract = new_contract(5);
 
         // accounts(1)がバイクを使用
-        contract.use_bike(0);
+        contract.use_bike(0, accounts(1));
 
         // accounts(2)でバイクを使用
         testing_env!(context.predecessor_account_id(accounts(2)).build());
-        contract.return_bike(0);
+        contract.return_bike(0, station());
+    }
+
+    // 使用者がバイクを修理中に変更->修理担当者が修理を完了し, 修理時間が記録されるか確認
+    #[test]
+    fn maintenance_by_user_and_mechanic() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.block_timestamp(100).build());
+        let mut contract = new_contract(5);
+        contract.add_mechanic(accounts(3));
+
+        // accounts(2)がバイクを使用し, 故障を報告
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .signer_account_id(accounts(2))
+            .build());
+        contract.use_bike(0, accounts(2));
+        contract.start_maintenance(0, Some(station()));
+        assert!(contract.is_in_maintenance(0));
+        assert_eq!(accounts(2), contract.who_reported_maintenance(0).unwrap());
+
+        // accounts(3)が修理を完了
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .block_timestamp(250)
+            .build());
+        contract.finish_maintenance(0);
+        assert!(contract.is_available(0));
+        assert_eq!(contract.maintenance_time(0).0, 150);
+    }
+
+    // 管理者以外が使用可能なバイクを修理中に変更->パニックを起こすか確認
+    #[test]
+    #[should_panic(expected = "Fail due to wrong account")]
+    fn maintenance_by_other_account() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = new_contract(5);
+
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        contract.start_maintenance(0, None);
+    }
+
+    // 修理中のバイクを使用->パニックを起こすか確認
+    #[test]
+    #[should_panic(expected = "Bike is not available")]
+    fn use_bike_in_maintenance() {
+        let context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = new_contract(5);
+
+        contract.start_maintenance(0, None);
+        contract.use_bike(0, accounts(1));
+    }
+
+    // 修理担当者以外が修理を完了->パニックを起こすか確認
+    #[test]
+    #[should_panic(expected = "Requires the Maintain permission")]
+    fn finish_maintenance_by_other_account() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = new_contract(5);
+
+        contract.start_maintenance(0, None);
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        contract.finish_maintenance(0);
+    }
+
+    // 点検完了後は, 間隔か使用回数が規定を超えるまで点検できないことを確認
+    #[test]
+    fn inspection_schedule() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.block_timestamp(0).build());
+        let mut contract = new_contract(2);
+        fund_reward_pool(&mut context, &mut contract);
+        contract.set_inspection_schedule(U64::from(1000), 2);
+
+        // 一度も点検されていないバイクは点検が必要
+        assert_eq!(contract.bikes_due_for_inspection(), vec![0, 1]);
+
+        // バイク0の点検が完了したとみなす
+        contract.inspect_bike(0);
+        testing_env_with_promise_result(
+            context.block_timestamp(10),
+            PromiseResult::Successful(vec![]),
+        );
+        contract.callback_return_bike(0, U128::from(10));
+        assert_eq!(contract.last_inspected_at(0).unwrap().0, 10);
+        assert_eq!(contract.bikes_due_for_inspection(), vec![1]);
+
+        // 2回使用されると点検が必要
+        contract.use_bike(0, accounts(1));
+        contract.return_bike(0, station());
+        assert!(!contract.is_due_for_inspection(0));
+        contract.use_bike(0, accounts(1));
+        contract.return_bike(0, station());
+        assert_eq!(contract.rides_since_inspection(0), 2);
+        assert!(contract.is_due_for_inspection(0));
+    }
+
+    // 時間が経過すると点検が必要になることを確認
+    #[test]
+    fn inspection_due_by_interval() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.block_timestamp(0).build());
+        let mut contract = new_contract(1);
+        contract.set_inspection_schedule(U64::from(1000), 2);
+        contract.bikes[0].last_inspected_at = Some(0);
+
+        testing_env!(context.block_timestamp(999).build());
+        assert!(!contract.is_due_for_inspection(0));
+        testing_env!(context.block_timestamp(1000).build());
+        assert!(contract.is_due_for_inspection(0));
+    }
+
+    // 点検が不要なバイクを点検->パニックを起こすか確認
+    #[test]
+    #[should_panic(expected = "Bike is not due for inspection")]
+    fn inspect_bike_not_due() {
+        let context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = new_contract(1);
+        contract.bikes[0].last_inspected_at = Some(env::block_timestamp());
+
+        contract.inspect_bike(0);
+    }
+
+    // 点検の最短時間が経過する前に返却->パニックを起こすか確認
+    #[test]
+    #[should_panic(expected = "Inspection is too short")]
+    fn return_inspection_too_short() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.block_timestamp(0).build());
+        let mut contract = new_contract(1);
+        fund_reward_pool(&mut context, &mut contract);
+        contract.set_inspection_rules(U64::from(100), U64::from(0));
+
+        contract.inspect_bike(0);
+        testing_env!(context.block_timestamp(99).build());
+        contract.return_bike(0, station());
+    }
+
+    // 直前にバイクを使用したアカウントが点検->パニックを起こすか確認
+    #[test]
+    #[should_panic(expected = "Last user cannot inspect the bike")]
+    fn inspect_by_last_user() {
+        let context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = new_contract(1);
+
+        contract.use_bike(0, accounts(1));
+        contract.return_bike(0, station());
+        contract.inspect_bike(0);
+    }
+
+    // 待機時間中に別のバイクを点検->パニックを起こすか確認
+    #[test]
+    #[should_panic(expected = "Inspector is in cooldown")]
+    fn inspect_in_cooldown() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.block_timestamp(0).build());
+        let mut contract = new_contract(2);
+        fund_reward_pool(&mut context, &mut contract);
+        contract.set_inspection_rules(U64::from(0), U64::from(100));
+
+        contract.inspect_bike(0);
+        assert_eq!(contract.last_inspection_of(accounts(1)).unwrap().0, 0);
+        testing_env!(context.block_timestamp(99).build());
+        contract.inspect_bike(1);
+    }
+
+    // 登録制の場合, 登録された点検者のみ点検できることを確認
+    #[test]
+    fn inspect_by_approved_inspector() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = new_contract(1);
+        fund_reward_pool(&mut context, &mut contract);
+        contract.set_open_inspection(false);
+        contract.add_inspector(accounts(2));
+        assert_eq!(contract.inspectors(), vec![accounts(2)]);
+
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        contract.inspect_bike(0);
+        assert_eq!(accounts(2), contract.who_is_inspecting(0).unwrap());
+    }
+
+    // 登録制の場合, 登録されていないアカウントが点検->パニックを起こすか確認
+    #[test]
+    #[should_panic(expected = "Only approved inspectors can inspect the bike")]
+    fn inspect_by_unapproved_account() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = new_contract(1);
+        contract.set_open_inspection(false);
+        contract.add_inspector(accounts(2));
+        contract.remove_inspector(accounts(2));
+
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        contract.inspect_bike(0);
+    }
+
+    // 使用が停止されている場合, ft_on_transferで受信したFTが返金されることを確認
+    #[test]
+    fn ft_on_transfer_while_rentals_paused() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = new_contract(1);
+        contract.emergency_stop();
+
+        match ft_transfer_call(
+            &mut context,
+            &mut contract,
+            base_token_id(),
+            AMOUNT_TO_USE_BIKE,
+            "0",
+        ) {
+            PromiseOrValue::Value(refund) => assert_eq!(refund.0, AMOUNT_TO_USE_BIKE),
+            PromiseOrValue::Promise(_) => panic!("Expected refund"),
+        }
+        assert!(contract.is_available(0));
+    }
+
+    // 停止中も使用中のバイクは返却できることを確認
+    #[test]
+    fn return_bike_while_paused() {
+        let context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = new_contract(1);
+
+        contract.use_bike(0, accounts(1));
+        contract.emergency_stop();
+        contract.return_bike(0, station());
+        assert!(contract.is_available(0));
+    }
+
+    // 点検が停止されている場合に点検->パニックを起こすか確認
+    #[test]
+    #[should_panic(expected = "Inspections are paused")]
+    fn inspect_while_paused() {
+        let context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = new_contract(1);
+        contract.set_pause_state(PauseState {
+            inspections: true,
+            ..Default::default()
+        });
+
+        contract.inspect_bike(0);
+    }
+
+    // 送信が停止されている場合に新規ユーザへFTを送信->パニックを起こすか確認
+    #[test]
+    #[should_panic(expected = "Payouts are paused")]
+    fn transfer_while_payouts_paused() {
+        let context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = new_contract(1);
+        contract.set_pause_state(PauseState {
+            payouts: true,
+            ..Default::default()
+        });
+
+        testing_env!(get_context(accounts(2)).build());
+        contract.transfer_ft_to_new_user(accounts(2), None);
+    }
+
+    // 使用料と資金の入金がTreasuryの内訳に記録されることを確認
+    #[test]
+    fn treasury_accounts() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = new_contract(1);
+
+        ft_transfer_call(
+            &mut context,
+            &mut contract,
+            base_token_id(),
+            AMOUNT_TO_USE_BIKE,
+            "0",
+        );
+        contract.return_bike(0, station());
+        ft_transfer_call(
+            &mut context,
+            &mut contract,
+            base_token_id(),
+            100,
+            "fund_reward_pool",
+        );
+        contract.allocate_revenue(TreasuryAccount::GiftBudget, U128::from(10));
+
+        // 使用料の半分は点検の報酬の資金に回ります
+        let summary = contract.treasury_summary();
+        assert_eq!(summary.revenue.0, AMOUNT_TO_USE_BIKE / 2 - 10);
+        assert_eq!(summary.reward_pool.0, 100 + AMOUNT_TO_USE_BIKE / 2);
+        assert_eq!(summary.gift_budget.0, 10);
+
+        // 引き出した分は使用料から差し引かれます
+        contract.withdraw_revenue(accounts(2), U128::from(AMOUNT_TO_USE_BIKE / 2 - 10));
+        assert_eq!(contract.treasury_summary().revenue.0, 0);
+    }
+
+    // 送信が失敗した場合, 差し引いたFTが元に戻ることを確認
+    #[test]
+    fn refund_treasury_on_failure() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = new_contract(1);
+        ft_transfer_call(
+            &mut context,
+            &mut contract,
+            base_token_id(),
+            AMOUNT_TO_USE_BIKE,
+            "0",
+        );
+        let revenue = contract.treasury_summary().revenue;
+        contract.withdraw_revenue(accounts(2), revenue);
+
+        testing_env_with_promise_result(
+            context.predecessor_account_id(accounts(0)),
+            PromiseResult::Failed,
+        );
+        contract.callback_refund_treasury(TreasuryAccount::Revenue, revenue);
+        assert_eq!(contract.treasury_summary().revenue, revenue);
+    }
+
+    // 使用料を超えて引き出し->パニックを起こすか確認
+    #[test]
+    #[should_panic(expected = "Not enough Revenue in treasury")]
+    fn withdraw_too_much_revenue() {
+        let context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = new_contract(1);
+
+        contract.withdraw_revenue(accounts(2), U128::from(1));
+    }
+
+    // 管理者以外が引き出し->パニックを起こすか確認
+    #[test]
+    #[should_panic(expected = "Requires the ManageTreasury permission")]
+    fn withdraw_revenue_by_other_account() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = new_contract(1);
+
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        contract.withdraw_revenue(accounts(2), U128::from(0));
+    }
+
+    // 点検の報酬の資金が足りない場合に点検->パニックを起こすか確認
+    #[test]
+    #[should_panic(expected = "Not enough RewardPool in treasury")]
+    fn inspect_without_reward_pool() {
+        let context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = new_contract(1);
+
+        contract.inspect_bike(0);
+    }
+
+    // 点検中は報酬が取り分けられ, 支払い後に残高から差し引かれることを確認
+    #[test]
+    fn commit_and_pay_reward() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.block_timestamp(0).build());
+        let mut contract = new_contract(2);
+        contract.set_inspection_rules(U64::from(0), U64::from(0));
+        fund_reward_pool(&mut context, &mut contract);
+
+        // 報酬は点検の報酬の資金の10%
+        let reward = contract.amount_reward_for_inspections().0;
+        assert_eq!(reward, 10);
+        contract.inspect_bike(0);
+        let summary = contract.treasury_summary();
+        assert_eq!(summary.reward_pool.0, 100 - reward);
+        assert_eq!(summary.committed_rewards.0, reward);
+
+        // 修理中に変更した場合, 取り分けた報酬はreward_poolに戻ります
+        contract.inspect_bike(1);
+        contract.start_maintenance(1, None);
+        assert_eq!(contract.treasury_summary().committed_rewards.0, reward);
+
+        contract.return_bike(0, station());
+        let summary = contract.treasury_summary();
+        assert_eq!(summary.committed_rewards.0, 0);
+        assert_eq!(summary.ft_balance.0, 100 - reward);
+    }
+
+    // 報酬の送信中に重ねて返却->パニックを起こすか確認
+    #[test]
+    #[should_panic(expected = "Reward payout is in progress")]
+    fn return_inspected_bike_twice() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.block_timestamp(0).build());
+        let mut contract = new_contract(1);
+        contract.set_inspection_rules(U64::from(0), U64::from(0));
+        fund_reward_pool(&mut context, &mut contract);
+
+        contract.inspect_bike(0);
+        contract.return_bike(0, station());
+        contract.return_bike(0, station());
+    }
+
+    // 支払いの停止中も点検を終えたバイクを返却でき, 停止の解除後に報酬を受け取れることを確認
+    #[test]
+    fn return_inspected_bike_while_payouts_paused() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.block_timestamp(0).build());
+        let mut contract = new_contract(1);
+        contract.set_inspection_rules(U64::from(0), U64::from(0));
+        contract.set_inspection_schedule(U64::from(0), 0);
+        fund_reward_pool(&mut context, &mut contract);
+        contract.set_pause_state(PauseState {
+            payouts: true,
+            ..Default::default()
+        });
+
+        let reward = contract.amount_reward_for_inspections();
+        contract.inspect_bike(0);
+        contract.return_bike(0, station());
+        assert!(matches!(contract.bikes[0].state, Bike::Available));
+        assert_eq!(contract.last_inspected_at(0).unwrap().0, 0);
+        assert_eq!(contract.treasury_summary().committed_rewards, reward);
+        assert_eq!(contract.unpaid_inspection_reward(accounts(1)), reward);
+
+        // 報酬を受け取る前でも, バイクを再び点検でき, 受け取っていない報酬に加えられます
+        let unpaid = reward.0 + contract.amount_reward_for_inspections().0;
+        contract.inspect_bike(0);
+        contract.return_bike(0, station());
+        assert_eq!(contract.unpaid_inspection_reward(accounts(1)).0, unpaid);
+
+        contract.set_pause_state(PauseState::default());
+        contract.claim_inspection_reward();
+        assert_eq!(contract.treasury_summary().committed_rewards.0, 0);
+        assert_eq!(contract.unpaid_inspection_reward(accounts(1)).0, 0);
+
+        // 送信が失敗した場合は, 受け取っていない報酬を元に戻します
+        testing_env_with_promise_result(&mut context, PromiseResult::Failed);
+        contract.callback_claim_inspection_reward(accounts(1), U128::from(unpaid));
+        assert_eq!(contract.unpaid_inspection_reward(accounts(1)).0, unpaid);
+        assert_eq!(contract.treasury_summary().committed_rewards.0, unpaid);
+    }
+
+    // ft_balance_ofの結果で残高が更新されることを確認
+    #[test]
+    fn sync_ft_balance() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = new_contract(1);
+
+        testing_env_with_promise_result(
+            context.predecessor_account_id(accounts(0)),
+            PromiseResult::Successful(b"\"42\"".to_vec()),
+        );
+        contract.callback_sync_ft_balance();
+        assert_eq!(contract.treasury_summary().ft_balance.0, 42);
+    }
+
+    // ft_transferで直接送信されたFTを, 残高の同期後に点検の報酬の資金に確保できることを確認
+    #[test]
+    fn allocate_unaccounted_ft() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = new_contract(1);
+        fund_reward_pool(&mut context, &mut contract);
+        let reward_pool = contract.treasury_summary().reward_pool.0;
+
+        // 内訳に含まれるFTに加えて, 50のFTが直接送信された
+        testing_env_with_promise_result(
+            context.predecessor_account_id(accounts(0)),
+            PromiseResult::Successful(format!("\"{}\"", reward_pool + 50).into_bytes()),
+        );
+        contract.callback_sync_ft_balance();
+
+        testing_env!(context.predecessor_account_id(accounts(0)).build());
+        assert_eq!(
+            contract
+                .allocate_unaccounted_ft(TreasuryAccount::RewardPool)
+                .0,
+            50
+        );
+        assert_eq!(contract.treasury_summary().reward_pool.0, reward_pool + 50);
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            contract.allocate_unaccounted_ft(TreasuryAccount::RewardPool)
+        }));
+        assert!(result.is_err());
+    }
+
+    // 使用料の分配の割合を変更すると, 報酬の資金と報酬の額に反映されることを確認
+    #[test]
+    fn revenue_share() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = new_contract(1);
+        contract.set_revenue_share(RevenueShare {
+            reward_pool_bps: 10000,
+            inspection_reward_bps: 5000,
+        });
+
+        ft_transfer_call(
+            &mut context,
+            &mut contract,
+            base_token_id(),
+            AMOUNT_TO_USE_BIKE,
+            "0",
+        );
+        contract.return_bike(0, station());
+        let summary = contract.treasury_summary();
+        assert_eq!(summary.revenue.0, 0);
+        assert_eq!(summary.reward_pool.0, AMOUNT_TO_USE_BIKE);
+        assert_eq!(
+            contract.amount_reward_for_inspections().0,
+            AMOUNT_TO_USE_BIKE / 2
+        );
+    }
+
+    // 100%を超える割合を設定->パニックを起こすか確認
+    #[test]
+    #[should_panic(expected = "Share must not exceed 10000 basis points")]
+    fn revenue_share_over_100_percent() {
+        let context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = new_contract(1);
+        contract.set_revenue_share(RevenueShare {
+            reward_pool_bps: 10001,
+            inspection_reward_bps: 0,
+        });
+    }
+
+    // 所有者が登録したバイクの使用料が, 手数料を差し引いて所有者の受け取れる使用料になることを確認
+    #[test]
+    fn rent_bike_registered_by_owner() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = new_contract(1);
+
+        // accounts(2)がバイクを登録
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        let index = contract.register_bike(U128::from(100), STATION.to_string());
+        assert_eq!(index, 1);
+        assert_eq!(contract.bike_owner(index), Some(accounts(2)));
+        assert_eq!(contract.bike_price(index).0, 100);
+
+        // accounts(3)がバイクを使用
+        testing_env!(context.signer_account_id(accounts(3)).build());
+        ft_transfer_call(
+            &mut context,
+            &mut contract,
+            base_token_id(),
+            100,
+            &index.to_string(),
+        );
+        assert_eq!(accounts(3), contract.who_is_using(index).unwrap());
+        // 使用料は返却時に精算するまで保持されます
+        assert_eq!(contract.earnings_of(accounts(2)).0, 0);
+        assert_eq!(contract.treasury_summary().held_fees.0, 100);
+
+        testing_env!(context.predecessor_account_id(accounts(3)).build());
+        contract.return_bike(index, station());
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+
+        // 手数料10%を差し引いた90ftを所有者が受け取れます
+        assert_eq!(contract.earnings_of(accounts(2)).0, 90);
+        let summary = contract.treasury_summary();
+        assert_eq!(summary.owner_earnings.0, 90);
+        assert_eq!(summary.revenue.0 + summary.reward_pool.0, 10);
+
+        contract.claim_earnings();
+        assert_eq!(contract.earnings_of(accounts(2)).0, 0);
+        assert_eq!(contract.treasury_summary().owner_earnings.0, 0);
+    }
+
+    // 使用可能なバイクは所有者が登録解除と再登録をできることを確認
+    #[test]
+    fn delist_and_relist_bike() {
+        let context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = new_contract(0);
+        let index = contract.register_bike(U128::from(AMOUNT_TO_USE_BIKE), STATION.to_string());
+
+        contract.delist_bike(index);
+        assert!(!contract.is_available(index));
+        contract.relist_bike(index);
+        assert!(contract.is_available(index));
+    }
+
+    // 所有者以外が登録解除->パニックを起こすか確認
+    #[test]
+    #[should_panic(expected = "Only the bike owner can call this method")]
+    fn delist_bike_by_other_account() {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = new_contract(0);
+        let index = contract.register_bike(U128::from(AMOUNT_TO_USE_BIKE), STATION.to_string());
+
+        testing_env!(context.predecessor_account_id(accounts(3)).build());
+        contract.delist_bike(index);
+    }
+
+    // 使用中のバイクを登録解除->パニックを起こすか確認
+    #[test]
+    #[should_panic(expected = "Bike is not available")]
+    fn delist_bike_in_use() {
+        let context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = new_contract(0);
+        let index = contract.register_bike(U128::from(AMOUNT_TO_USE_BIKE), STATION.to_string());
+
+        contract.use_bike(index, accounts(2));
+        contract.delist_bike(index);
+    }
+
+    // 所有者ごとの上限を超えてバイクを登録->パニックを起こすか確認
+    #[test]
+    #[should_panic(expected = "Listing limit per owner reached")]
+    fn register_bike_over_limit() {
+        let context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = new_contract(0);
+
+        for _ in 0..=DEFAULT_MAX_LISTINGS_PER_OWNER {
+            contract.register_bike(U128::from(AMOUNT_TO_USE_BIKE), STATION.to_string());
+        }
+    }
+
+    // 範囲外の料金でバイクを登録->パニックを起こすか確認
+    #[test]
+    #[should_panic(expected = "Price must be between")]
+    fn register_bike_with_price_out_of_range() {
+        let context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = new_contract(0);
+
+        contract.register_bike(U128::from(DEFAULT_MAX_PRICE + 1), STATION.to_string());
+    }
+
+    // 追加したトークンで使用料を支払えること, 支払いに使用したトークンが記録されることを確認
+    #[test]
+    fn rent_bike_with_accepted_token() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = new_contract(1);
+        let token_id: AccountId = "usdc.testnet".parse().unwrap();
+        contract.add_accepted_token(token_id.clone(), U128::from(5));
+        assert_eq!(contract.token_price(token_id.clone()).unwrap().0, 5);
+
+        ft_transfer_call(&mut context, &mut contract, token_id.clone(), 5, "0");
+        assert_eq!(accounts(1), contract.who_is_using(0).unwrap());
+        assert_eq!(
+            contract.payment_currency_of(0),
+            Some(Currency::Ft(token_id.clone()))
+        );
+        assert_eq!(contract.token_revenue_of(token_id.clone()).0, 0);
+
+        contract.return_bike(0, station());
+        assert_eq!(contract.payment_currency_of(0), None);
+        // FT_CONTRACT_ACCOUNT以外のトークンの使用料は返却時に別に記録されます
+        assert_eq!(contract.token_revenue_of(token_id.clone()).0, 5);
+        assert_eq!(contract.treasury_summary().revenue.0, 0);
+
+        contract.withdraw_token_revenue(token_id.clone(), accounts(1), U128::from(5));
+        assert_eq!(contract.token_revenue_of(token_id).0, 0);
+    }
+
+    // 追加されていないトークンで使用料を支払う->パニックを起こすか確認
+    #[test]
+    #[should_panic(expected = "Token is not accepted")]
+    fn rent_bike_with_unknown_token() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = new_contract(1);
+
+        ft_transfer_call(
+            &mut context,
+            &mut contract,
+            "unknown.testnet".parse().unwrap(),
+            AMOUNT_TO_USE_BIKE,
+            "0",
+        );
+    }
+
+    // 所有者がいるバイクをFT_CONTRACT_ACCOUNT以外のトークンで使用->パニックを起こすか確認
+    #[test]
+    #[should_panic(expected = "Bike owned by an account accepts only")]
+    fn rent_owned_bike_with_other_token() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = new_contract(0);
+        let token_id: AccountId = "usdc.testnet".parse().unwrap();
+        contract.add_accepted_token(token_id.clone(), U128::from(5));
+        let index = contract.register_bike(U128::from(5), STATION.to_string());
+
+        ft_transfer_call(&mut context, &mut contract, token_id, 5, &index.to_string());
+    }
+
+    // NEARでバイクを使用し, 返却時にNEARの使用料として精算されることを確認
+    #[test]
+    fn rent_bike_with_near() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context
+            .attached_deposit(DEFAULT_NEAR_PRICE_TO_USE_BIKE * 2)
+            .build());
+        let mut contract = new_contract(1);
+
+        contract.rent_with_near(0);
+        assert_eq!(accounts(1), contract.who_is_using(0).unwrap());
+        assert_eq!(contract.payment_currency_of(0), Some(Currency::Near));
+        assert_eq!(contract.near_revenue().0, 0);
+
+        testing_env!(context.attached_deposit(0).build());
+        contract.return_bike(0, station());
+        assert_eq!(contract.near_revenue().0, DEFAULT_NEAR_PRICE_TO_USE_BIKE);
+
+        contract.withdraw_near_revenue(accounts(1), contract.near_revenue());
+        assert_eq!(contract.near_revenue().0, 0);
+    }
+
+    // NEARが足りない状態でバイクを使用->パニックを起こすか確認
+    #[test]
+    #[should_panic(expected = "yoctoNEAR to use the bike")]
+    fn rent_bike_with_not_enough_near() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context
+            .attached_deposit(DEFAULT_NEAR_PRICE_TO_USE_BIKE - 1)
+            .build());
+        let mut contract = new_contract(1);
+
+        contract.rent_with_near(0);
+    }
+
+    // 定期券を購入し, 1日に使用できる回数まで使用料を支払わずにバイクを使用できることを確認
+    #[test]
+    fn rent_bike_with_pass() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = new_contract(1);
+        assert!(!contract.has_active_pass(accounts(1)));
+
+        ft_transfer_call(
+            &mut context,
+            &mut contract,
+            base_token_id(),
+            DEFAULT_DAY_PASS_PRICE,
+            "buy_pass:day",
+        );
+        assert!(contract.has_active_pass(accounts(1)));
+        assert_eq!(
+            contract.treasury_summary().ft_balance.0,
+            DEFAULT_DAY_PASS_PRICE
+        );
+
+        for _ in 0..DEFAULT_PASS_DAILY_RIDE_CAP {
+            contract.rent_with_pass(0);
+            assert_eq!(accounts(1), contract.who_is_using(0).unwrap());
+            assert_eq!(contract.payment_currency_of(0), None);
+            contract.return_bike(0, station());
+        }
+        assert_eq!(
+            contract.pass_of(accounts(1)).unwrap().rides_today,
+            DEFAULT_PASS_DAILY_RIDE_CAP
+        );
+
+        // 有効期限内に購入した場合は有効期限を延長します
+        let expires_at = contract.pass_of(accounts(1)).unwrap().expires_at.0;
+        ft_transfer_call(
+            &mut context,
+            &mut contract,
+            base_token_id(),
+            DEFAULT_WEEK_PASS_PRICE,
+            "buy_pass:week",
+        );
+        assert_eq!(
+            contract.pass_of(accounts(1)).unwrap().expires_at.0,
+            expires_at + 7 * 24 * 60 * 60 * 1_000_000_000
+        );
+    }
+
+    // 1日に使用できる回数を超えて定期券でバイクを使用->パニックを起こすか確認
+    #[test]
+    #[should_panic(expected = "Daily ride cap of the pass is reached")]
+    fn rent_bike_with_pass_over_daily_cap() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = new_contract(1);
+        ft_transfer_call(
+            &mut context,
+            &mut contract,
+            base_token_id(),
+            DEFAULT_DAY_PASS_PRICE,
+            "buy_pass:day",
+        );
+
+        for _ in 0..=DEFAULT_PASS_DAILY_RIDE_CAP {
+            contract.rent_with_pass(0);
+            contract.return_bike(0, station());
+        }
+    }
+
+    // 有効期限が切れた定期券でバイクを使用->パニックを起こすか確認
+    #[test]
+    #[should_panic(expected = "No active pass")]
+    fn rent_bike_with_expired_pass() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = new_contract(1);
+        ft_transfer_call(
+            &mut context,
+            &mut contract,
+            base_token_id(),
+            DEFAULT_DAY_PASS_PRICE,
+            "buy_pass:day",
+        );
+
+        testing_env!(context
+            .block_timestamp(24 * 60 * 60 * 1_000_000_000)
+            .build());
+        contract.rent_with_pass(0);
+    }
+
+    // 定期券での使用にも預り金が必要で, 同じアカウントの預り金はまとめて保持されることを確認
+    #[test]
+    fn rent_bike_with_pass_holds_deposit() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = new_contract(1);
+        contract.set_security_deposit_bps(10000);
+        contract.set_inspection_schedule(U64::from(u64::MAX), u32::MAX);
+        ft_transfer_call(
+            &mut context,
+            &mut contract,
+            base_token_id(),
+            DEFAULT_DAY_PASS_PRICE,
+            "buy_pass:day",
+        );
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            contract.rent_with_pass(0);
+        }));
+        assert!(result.is_err());
+
+        for _ in 0..2 {
+            testing_env!(context
+                .attached_deposit(DEFAULT_NEAR_PRICE_TO_USE_BIKE)
+                .build());
+            contract.rent_with_pass(0);
+            testing_env!(context.attached_deposit(0).build());
+            contract.return_bike(0, station());
+        }
+        let deposits = contract.deposits_of(0);
+        assert_eq!(deposits.len(), 1);
+        assert_eq!(deposits[0].currency, Currency::Near);
+        assert_eq!(deposits[0].amount.0, 2 * DEFAULT_NEAR_PRICE_TO_USE_BIKE);
+    }
+
+    // プロモーションを作成
+    fn create_promo_campaign(contract: &mut Contract, code: &str, discount: Discount) {
+        contract.create_promo_campaign(
+            "campaign".to_string(),
+            Base64VecU8::from(env::sha256(code.as_bytes())),
+            discount,
+            2,
+            1,
+            U64::from(1_000_000_000),
+        );
+    }
+
+    // プロモーションコードで割り引かれた分が返金されることを確認
+    #[test]
+    fn rent_bike_with_promo_code() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = new_contract(1);
+        create_promo_campaign(&mut contract, "SPRING", Discount::Percent(50));
+
+        let refund = ft_transfer_call(
+            &mut context,
+            &mut contract,
+            base_token_id(),
+            AMOUNT_TO_USE_BIKE,
+            "0:SPRING",
+        );
+        assert!(matches!(refund, PromiseOrValue::Value(U128(n)) if n == AMOUNT_TO_USE_BIKE / 2));
+        assert_eq!(
+            contract.treasury_summary().ft_balance.0,
+            AMOUNT_TO_USE_BIKE / 2
+        );
+        assert_eq!(
+            contract
+                .promo_campaign("campaign".to_string())
+                .unwrap()
+                .uses,
+            1
+        );
+        assert_eq!(
+            contract.promo_uses_of("campaign".to_string(), accounts(1)),
+            1
+        );
+    }
+
+    // 1つのアカウントが使用できる回数を超えてプロモーションコードを使用->パニックを起こすか確認
+    #[test]
+    #[should_panic(expected = "Promo code usage limit reached for the account")]
+    fn rent_bike_with_promo_code_over_account_limit() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = new_contract(1);
+        create_promo_campaign(&mut contract, "SPRING", Discount::Fixed(U128::from(10)));
+
+        ft_transfer_call(
+            &mut context,
+            &mut contract,
+            base_token_id(),
+            AMOUNT_TO_USE_BIKE,
+            "0:SPRING",
+        );
+        contract.return_bike(0, station());
+        ft_transfer_call(
+            &mut context,
+            &mut contract,
+            base_token_id(),
+            AMOUNT_TO_USE_BIKE,
+            "0:SPRING",
+        );
+    }
+
+    // 所有者がいるバイクにプロモーションコードを使用->パニックを起こすか確認
+    #[test]
+    #[should_panic(expected = "Promo code cannot be used for a bike owned by an account")]
+    fn rent_owned_bike_with_promo_code() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = new_contract(1);
+        create_promo_campaign(&mut contract, "SPRING", Discount::Percent(50));
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        let index = contract.register_bike(U128::from(AMOUNT_TO_USE_BIKE), STATION.to_string());
+
+        testing_env!(context.signer_account_id(accounts(3)).build());
+        ft_transfer_call(
+            &mut context,
+            &mut contract,
+            base_token_id(),
+            AMOUNT_TO_USE_BIKE,
+            &format!("{}:SPRING", index),
+        );
+    }
+
+    // FT_CONTRACT_ACCOUNT以外のトークンで固定額のプロモーションコードを使用->パニックを起こすか確認
+    #[test]
+    #[should_panic(expected = "Fixed discount applies only to")]
+    fn rent_bike_with_fixed_promo_code_in_other_token() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = new_contract(1);
+        let token_id: AccountId = "usdc.testnet".parse().unwrap();
+        contract.add_accepted_token(token_id.clone(), U128::from(5));
+        create_promo_campaign(&mut contract, "SPRING", Discount::Fixed(U128::from(10)));
+
+        ft_transfer_call(&mut context, &mut contract, token_id, 5, "0:SPRING");
+    }
+
+    // 有効期限が切れたプロモーションコードを使用->パニックを起こすか確認
+    #[test]
+    #[should_panic(expected = "Promo code is expired")]
+    fn rent_bike_with_expired_promo_code() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = new_contract(1);
+        create_promo_campaign(&mut contract, "SPRING", Discount::Percent(50));
+
+        testing_env!(context.block_timestamp(1_000_000_000).build());
+        ft_transfer_call(
+            &mut context,
+            &mut contract,
+            base_token_id(),
+            AMOUNT_TO_USE_BIKE,
+            "0:SPRING",
+        );
+    }
+
+    // 紹介されたアカウントが初めてバイクの使用を終えた際に, 紹介者が報酬を受け取れることを確認
+    #[test]
+    fn reward_referrer_after_first_ride() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = new_contract(1);
+        ft_transfer_call(
+            &mut context,
+            &mut contract,
+            base_token_id(),
+            100,
+            "fund_gift_budget",
+        );
+
+        testing_env!(get_context(accounts(2)).build());
+        contract.transfer_ft_to_new_user(accounts(2), Some(accounts(3)));
+        assert_eq!(
+            contract.referral_of(accounts(2)).unwrap().referrer_id,
+            accounts(3)
+        );
+        assert_eq!(
+            contract.referrer_info(accounts(3)).referees,
+            vec![accounts(2)]
+        );
+
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        for _ in 0..2 {
+            ft_transfer_call(
+                &mut context,
+                &mut contract,
+                base_token_id(),
+                AMOUNT_TO_USE_BIKE,
+                "0",
+            );
+            contract.return_bike(0, station());
+        }
+        // 報酬は初めての使用の後に1回のみ支払われます
+        assert_eq!(
+            contract.referral_of(accounts(2)).unwrap().reward.unwrap().0,
+            DEFAULT_REFERRAL_BONUS
+        );
+        assert_eq!(
+            contract.referrer_info(accounts(3)).unclaimed_rewards.0,
+            DEFAULT_REFERRAL_BONUS
+        );
+        assert_eq!(
+            contract.treasury_summary().referral_rewards.0,
+            DEFAULT_REFERRAL_BONUS
+        );
+
+        testing_env!(get_context(accounts(3)).build());
+        contract.claim_referral_rewards();
+        let info = contract.referrer_info(accounts(3));
+        assert_eq!(info.unclaimed_rewards.0, 0);
+        assert_eq!(info.total_rewards.0, DEFAULT_REFERRAL_BONUS);
+    }
+
+    // 自分自身を紹介者として指定->パニックを起こすか確認
+    #[test]
+    #[should_panic(expected = "Account cannot refer itself")]
+    fn refer_self() {
+        let context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = new_contract(1);
+
+        testing_env!(get_context(accounts(2)).build());
+        contract.transfer_ft_to_new_user(accounts(2), Some(accounts(2)));
+    }
+
+    // 他のアカウントを新規ユーザとして紹介を記録->パニックを起こすか確認
+    #[test]
+    #[should_panic(expected = "Only the new user can call this method")]
+    fn refer_other_account() {
+        let context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = new_contract(1);
+
+        contract.transfer_ft_to_new_user(accounts(2), Some(accounts(3)));
+    }
+
+    // 新規ユーザへの送信を重ねて受け取る->パニックを起こすか確認
+    #[test]
+    #[should_panic(expected = "Account has already received the gift")]
+    fn transfer_ft_to_new_user_twice() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = new_contract(1);
+        ft_transfer_call(
+            &mut context,
+            &mut contract,
+            base_token_id(),
+            100,
+            "fund_gift_budget",
+        );
+
+        testing_env!(get_context(accounts(2)).build());
+        contract.transfer_ft_to_new_user(accounts(2), None);
+        contract.transfer_ft_to_new_user(accounts(2), None);
+    }
+
+    // 送信が失敗した場合, 改めて新規ユーザへの送信を受け取れることを確認
+    #[test]
+    fn transfer_ft_to_new_user_after_failure() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = new_contract(1);
+        ft_transfer_call(
+            &mut context,
+            &mut contract,
+            base_token_id(),
+            100,
+            "fund_gift_budget",
+        );
+
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        contract.transfer_ft_to_new_user(accounts(2), None);
+        testing_env_with_promise_result(&mut context, PromiseResult::Successful(b"false".to_vec()));
+        contract.callback_transfer_ft_to_new_user(accounts(2));
+        testing_env!(context.build());
+        contract.transfer_ft_to_new_user(accounts(2), None);
+    }
+
+    // すでにバイクを使用したアカウントが紹介を記録->パニックを起こすか確認
+    #[test]
+    #[should_panic(expected = "Account is not a new user")]
+    fn refer_existing_rider() {
+        let context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = new_contract(1);
+
+        let mut context = get_context(accounts(2));
+        ft_transfer_call(
+            &mut context,
+            &mut contract,
+            base_token_id(),
+            AMOUNT_TO_USE_BIKE,
+            "0",
+        );
+        contract.return_bike(0, station());
+        contract.transfer_ft_to_new_user(accounts(2), Some(accounts(3)));
+    }
+
+    // バイクの使用時間に応じてポイントが付与され, 会員ランクによって使用料が割り引かれることを確認
+    #[test]
+    fn add_loyalty_points_and_discount() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = new_contract(1);
+
+        ft_transfer_call(
+            &mut context,
+            &mut contract,
+            base_token_id(),
+            AMOUNT_TO_USE_BIKE,
+            "0",
+        );
+        testing_env!(context
+            .block_timestamp(DEFAULT_BRONZE_POINTS as u64 * 60 * 1_000_000_000)
+            .build());
+        contract.return_bike(0, station());
+        let loyalty = contract.loyalty_of(accounts(1));
+        assert_eq!(loyalty.points.0, DEFAULT_BRONZE_POINTS);
+        assert_eq!(loyalty.tier, LoyaltyTier::Bronze);
+
+        let refund = ft_transfer_call(
+            &mut context,
+            &mut contract,
+            base_token_id(),
+            AMOUNT_TO_USE_BIKE,
+            "0",
+        );
+        let discount = AMOUNT_TO_USE_BIKE * DEFAULT_BRONZE_DISCOUNT as u128 / 100;
+        assert!(matches!(refund, PromiseOrValue::Value(U128(n)) if n == discount));
+    }
+
+    // ポイントを交換して無料でバイクを使用できることを確認
+    #[test]
+    fn redeem_points_for_free_ride() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = new_contract(1);
+
+        ft_transfer_call(
+            &mut context,
+            &mut contract,
+            base_token_id(),
+            AMOUNT_TO_USE_BIKE,
+            "0",
+        );
+        testing_env!(context
+            .block_timestamp(DEFAULT_FREE_RIDE_POINTS as u64 * 60 * 1_000_000_000)
+            .build());
+        contract.return_bike(0, station());
+
+        contract.redeem_points(0);
+        assert_eq!(accounts(1), contract.who_is_using(0).unwrap());
+        assert_eq!(contract.loyalty_of(accounts(1)).points.0, 0);
+    }
+
+    // ポイントを交換した使用ではポイントが付与されず, 1回の使用のポイントには上限があることを確認
+    #[test]
+    fn no_loyalty_points_for_free_ride() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = new_contract(1);
+
+        ft_transfer_call(
+            &mut context,
+            &mut contract,
+            base_token_id(),
+            AMOUNT_TO_USE_BIKE,
+            "0",
+        );
+        testing_env!(context
+            .block_timestamp(2 * DEFAULT_MAX_POINTS_PER_RIDE as u64 * 60 * 1_000_000_000)
+            .build());
+        contract.return_bike(0, station());
+        assert_eq!(
+            contract.loyalty_of(accounts(1)).points.0,
+            DEFAULT_MAX_POINTS_PER_RIDE
+        );
+
+        contract.redeem_points(0);
+        testing_env!(context
+            .block_timestamp(4 * DEFAULT_MAX_POINTS_PER_RIDE as u64 * 60 * 1_000_000_000)
+            .build());
+        contract.return_bike(0, station());
+        assert_eq!(contract.loyalty_of(accounts(1)).points.0, 0);
+    }
+
+    // ポイントが足りない状態でポイントを交換->パニックを起こすか確認
+    #[test]
+    #[should_panic(expected = "Not enough loyalty points")]
+    fn redeem_not_enough_points() {
+        let context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = new_contract(1);
+
+        contract.redeem_points(0);
+    }
+
+    // 預り金がバイクの使用後も保持され, 点検が完了した後に引き出せることを確認
+    #[test]
+    fn release_deposit_after_inspection() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = new_contract(1);
+        contract.set_security_deposit_bps(10000);
+        contract.set_inspection_rules(U64::from(0), U64::from(0));
+        fund_reward_pool(&mut context, &mut contract);
+
+        ft_transfer_call(
+            &mut context,
+            &mut contract,
+            base_token_id(),
+            2 * AMOUNT_TO_USE_BIKE,
+            "0",
+        );
+        contract.return_bike(0, station());
+        assert_eq!(contract.deposits_of(0)[0].amount.0, AMOUNT_TO_USE_BIKE);
+        assert_eq!(contract.treasury_summary().deposits.0, AMOUNT_TO_USE_BIKE);
+
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        contract.inspect_bike(0);
+        contract.return_bike(0, station());
+        testing_env_with_promise_result(&mut context, PromiseResult::Successful(vec![]));
+        contract.callback_return_bike(0, contract.amount_reward_for_inspections());
+        assert!(contract.deposits_of(0).is_empty());
+        let currency = Currency::Ft(base_token_id());
+        assert_eq!(
+            contract.deposit_balance_of(accounts(1), currency.clone()).0,
+            AMOUNT_TO_USE_BIKE
+        );
+
+        testing_env!(get_context(accounts(1)).build());
+        contract.withdraw_deposit(currency.clone());
+        assert_eq!(contract.deposit_balance_of(accounts(1), currency).0, 0);
+        assert_eq!(contract.treasury_summary().deposits.0, 0);
+    }
+
+    // 点検者が修理を報告したバイクの預り金を管理者が受け取れることを確認
+    #[test]
+    fn claim_deposit_of_damaged_bike() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = new_contract(1);
+        contract.set_security_deposit_bps(10000);
+        contract.set_inspection_rules(U64::from(0), U64::from(0));
+        fund_reward_pool(&mut context, &mut contract);
+
+        ft_transfer_call(
+            &mut context,
+            &mut contract,
+            base_token_id(),
+            2 * AMOUNT_TO_USE_BIKE,
+            "0",
+        );
+        contract.return_bike(0, station());
+        testing_env!(get_context(accounts(2)).build());
+        contract.inspect_bike(0);
+        contract.start_maintenance(0, None);
+
+        // 異議申し立てができる時間を過ぎてから受け取ります
+        testing_env!(get_context(accounts(1))
+            .block_timestamp(DEFAULT_DISPUTE_WINDOW + 1)
+            .build());
+        let revenue = contract.treasury_summary().revenue.0;
+        contract.claim_deposit(0, accounts(1), U128::from(10));
+        assert_eq!(contract.treasury_summary().revenue.0, revenue + 10);
+
+        // 残りの預り金は修理の完了後に引き出せます
+        contract.finish_maintenance(0);
+        assert_eq!(
+            contract
+                .deposit_balance_of(accounts(1), Currency::Ft(base_token_id()))
+                .0,
+            AMOUNT_TO_USE_BIKE - 10
+        );
+    }
+
+    // 修理の報告で責任があるとされたアカウントの預り金は, 修理の完了後も保持され受け取れることを確認
+    #[test]
+    fn claim_deposit_after_maintenance() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = new_contract(1);
+        contract.set_security_deposit_bps(10000);
+        contract.set_inspection_rules(U64::from(0), U64::from(0));
+        fund_reward_pool(&mut context, &mut contract);
+
+        ft_transfer_call(
+            &mut context,
+            &mut contract,
+            base_token_id(),
+            2 * AMOUNT_TO_USE_BIKE,
+            "0",
+        );
+        contract.return_bike(0, station());
+        testing_env!(get_context(accounts(2)).build());
+        contract.inspect_bike(0);
+        contract.start_maintenance(0, None);
+
+        testing_env!(get_context(accounts(1)).build());
+        contract.finish_maintenance(0);
+        assert_eq!(contract.deposits_of(0)[0].amount.0, AMOUNT_TO_USE_BIKE);
+        let currency = Currency::Ft(base_token_id());
+        assert_eq!(
+            contract.deposit_balance_of(accounts(1), currency.clone()).0,
+            0
+        );
+
+        testing_env!(get_context(accounts(1))
+            .block_timestamp(DEFAULT_DISPUTE_WINDOW + 1)
+            .build());
+        let revenue = contract.treasury_summary().revenue.0;
+        contract.claim_deposit(0, accounts(1), U128::from(10));
+        assert_eq!(contract.treasury_summary().revenue.0, revenue + 10);
+
+        // 修理の報告は精算済みのため, それ以上は受け取れません
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            contract.claim_deposit(0, accounts(1), U128::from(10))
+        }));
+        assert!(result.is_err());
+    }
+
+    // 異議申し立てができる時間を過ぎた修理の報告の預り金は, 次の修理の完了で引き出せることを確認
+    #[test]
+    fn release_reported_deposit_after_window() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = new_contract(1);
+        contract.set_security_deposit_bps(10000);
+        contract.set_inspection_rules(U64::from(0), U64::from(0));
+        fund_reward_pool(&mut context, &mut contract);
+
+        ft_transfer_call(
+            &mut context,
+            &mut contract,
+            base_token_id(),
+            2 * AMOUNT_TO_USE_BIKE,
+            "0",
+        );
+        contract.return_bike(0, station());
+        testing_env!(get_context(accounts(2)).build());
+        contract.inspect_bike(0);
+        contract.start_maintenance(0, None);
+        testing_env!(get_context(accounts(1)).build());
+        contract.finish_maintenance(0);
+
+        testing_env!(get_context(accounts(1))
+            .block_timestamp(DEFAULT_DISPUTE_WINDOW + 1)
+            .build());
+        contract.start_maintenance(0, None);
+        contract.finish_maintenance(0);
+        assert!(contract.deposits_of(0).is_empty());
+        assert_eq!(
+            contract
+                .deposit_balance_of(accounts(1), Currency::Ft(base_token_id()))
+                .0,
+            AMOUNT_TO_USE_BIKE
+        );
+    }
+
+    // 預り金が足りない状態でバイクを使用->パニックを起こすか確認
+    #[test]
+    #[should_panic(expected = "Require 60 ft to use the bike")]
+    fn rent_bike_without_deposit() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = new_contract(1);
+        contract.set_security_deposit_bps(10000);
+
+        ft_transfer_call(
+            &mut context,
+            &mut contract,
+            base_token_id(),
+            AMOUNT_TO_USE_BIKE,
+            "0",
+        );
+    }
+
+    // 点検者が修理を報告し, 使用していたアカウントが異議を申し立てる
+    fn report_damage_and_open_dispute(
+        context: &mut VMContextBuilder,
+        contract: &mut Contract,
+    ) -> u64 {
+        contract.set_security_deposit_bps(10000);
+        contract.set_inspection_rules(U64::from(0), U64::from(0));
+        fund_reward_pool(context, contract);
+        ft_transfer_call(
+            context,
+            contract,
+            base_token_id(),
+            2 * AMOUNT_TO_USE_BIKE,
+            "0",
+        );
+        contract.return_bike(0, station());
+
+        testing_env!(get_context(accounts(2)).build());
+        contract.inspect_bike(0);
+        contract.start_maintenance(0, None);
+
+        testing_env!(get_context(accounts(1)).build());
+        contract.open_dispute(0, "The bike was fine".to_string())
+    }
+
+    // 異議が認められた場合, 預り金を引き出せることを確認
+    #[test]
+    fn resolve_dispute_in_favor_of_renter() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = new_contract(1);
+        let dispute_id = report_damage_and_open_dispute(&mut context, &mut contract);
+        let dispute = contract.get_dispute(dispute_id).unwrap();
+        assert_eq!(dispute.status, DisputeStatus::Open);
+        assert_eq!(dispute.amount.0, AMOUNT_TO_USE_BIKE);
+
+        // 異議申し立て中の預り金は, 修理が完了しても保持されます
+        contract.finish_maintenance(0);
+        assert_eq!(contract.deposits_of(0)[0].dispute_id, Some(dispute_id));
+
+        contract.add_arbiter(accounts(3));
+        testing_env!(get_context(accounts(3)).build());
+        contract.resolve_dispute(dispute_id, true);
+        let dispute = contract.get_dispute(dispute_id).unwrap();
+        assert_eq!(dispute.status, DisputeStatus::Upheld);
+        assert_eq!(dispute.resolved_by, Some(accounts(3)));
+        assert_eq!(
+            contract
+                .deposit_balance_of(accounts(1), Currency::Ft(base_token_id()))
+                .0,
+            AMOUNT_TO_USE_BIKE
+        );
+    }
+
+    // 異議が認められなかった場合, 預り金が使用料として記録されることを確認
+    #[test]
+    fn reject_dispute() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = new_contract(1);
+        let dispute_id = report_damage_and_open_dispute(&mut context, &mut contract);
+
+        let revenue = contract.treasury_summary().revenue.0;
+        contract.resolve_dispute(dispute_id, false);
+        assert_eq!(
+            contract.get_dispute(dispute_id).unwrap().status,
+            DisputeStatus::Rejected
+        );
+        assert_eq!(
+            contract.treasury_summary().revenue.0,
+            revenue + AMOUNT_TO_USE_BIKE
+        );
+        assert!(contract.deposits_of(0).is_empty());
+    }
+
+    // 異議申し立てができる時間を過ぎてから異議を申し立てる->パニックを起こすか確認
+    #[test]
+    #[should_panic(expected = "Dispute window has passed")]
+    fn open_dispute_after_window() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = new_contract(1);
+        contract.set_dispute_window(U64::from(0));
+        contract.set_security_deposit_bps(10000);
+        contract.set_inspection_rules(U64::from(0), U64::from(0));
+        fund_reward_pool(&mut context, &mut contract);
+        ft_transfer_call(
+            &mut context,
+            &mut contract,
+            base_token_id(),
+            2 * AMOUNT_TO_USE_BIKE,
+            "0",
+        );
+        contract.return_bike(0, station());
+        testing_env!(get_context(accounts(2)).build());
+        contract.inspect_bike(0);
+        contract.start_maintenance(0, None);
+
+        testing_env!(context.block_timestamp(1).build());
+        contract.open_dispute(0, "The bike was fine".to_string());
+    }
+
+    // 返却したステーションが記録され, ステーションごとの使用可能なバイクを取得できることを確認
+    #[test]
+    fn return_bike_to_station() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = new_contract(2);
+        let location = Location {
+            lat: 356_812,
+            lon: 1_397_671,
+        };
+        contract.add_station("tokyo".to_string(), location.clone(), 10);
+
+        ft_transfer_call(
+            &mut context,
+            &mut contract,
+            base_token_id(),
+            AMOUNT_TO_USE_BIKE,
+            "1",
+        );
+        contract.return_bike(1, ReturnLocation::Coordinates(location));
+        assert_eq!(contract.bike_station(1), Some("tokyo".to_string()));
+        assert_eq!(contract.available_bikes_at("tokyo".to_string()), vec![1]);
+        let tokyo = contract
+            .stations()
+            .into_iter()
+            .find(|station| station.station_id == "tokyo")
+            .unwrap();
+        assert_eq!(tokyo.num_of_bikes, 1);
+    }
+
+    // 登録されていない場所へ返却->パニックを起こすか確認
+    #[test]
+    #[should_panic(expected = "Unknown location")]
+    fn return_bike_to_unknown_location() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = new_contract(1);
+
+        ft_transfer_call(
+            &mut context,
+            &mut contract,
+            base_token_id(),
+            AMOUNT_TO_USE_BIKE,
+            "0",
+        );
+        contract.return_bike(0, ReturnLocation::Coordinates(Location { lat: 1, lon: 1 }));
+    }
+
+    // 空きのないステーションへ返却->パニックを起こすか確認
+    #[test]
+    #[should_panic(expected = "Station is full")]
+    fn return_bike_to_full_station() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = new_contract(2);
+        contract.add_station("small".to_string(), Location { lat: 1, lon: 1 }, 1);
+        let small = ReturnLocation::Station("small".to_string());
+
+        ft_transfer_call(
+            &mut context,
+            &mut contract,
+            base_token_id(),
+            AMOUNT_TO_USE_BIKE,
+            "0",
+        );
+        contract.return_bike(0, small);
+        ft_transfer_call(
+            &mut context,
+            &mut contract,
+            base_token_id(),
+            AMOUNT_TO_USE_BIKE,
+            "1",
+        );
+        contract.return_bike(1, ReturnLocation::Station("small".to_string()));
+    }
+
+    // 借りたステーションと異なるステーションへ返却した場合, 預り金から手数料を受け取ることを確認
+    #[test]
+    fn charge_rebalancing_fee() {
+        const FEE: u128 = 10;
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = new_contract(1);
+        contract.add_station("other".to_string(), Location { lat: 1, lon: 1 }, 10);
+        contract.set_inspection_schedule(U64::from(u64::MAX), u32::MAX);
+        contract.set_rebalancing_config(RebalancingConfig {
+            adjustment: RebalancingAdjustment::Fee {
+                amount: U128::from(FEE),
+            },
+            relocation_reward: U128::from(0),
+        });
+
+        // 預り金を受け取らない設定でも, 使用時に手数料を受け取ります
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            ft_transfer_call(
+                &mut context,
+                &mut contract,
+                base_token_id(),
+                AMOUNT_TO_USE_BIKE,
+                "0",
+            );
+        }));
+        assert!(result.is_err());
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+
+        // 同じステーションへ返却した場合は手数料を預り金として保持します
+        let revenue = contract.treasury_summary().revenue.0;
+        ft_transfer_call(
+            &mut context,
+            &mut contract,
+            base_token_id(),
+            AMOUNT_TO_USE_BIKE + FEE,
+            "0",
+        );
+        contract.return_bike(0, station());
+        let summary = contract.treasury_summary();
+        let rental_revenue = summary.revenue.0 - revenue;
+        assert_eq!(summary.deposits.0, FEE);
+
+        // 異なるステーションへ返却した場合は手数料を受け取ります
+        ft_transfer_call(
+            &mut context,
+            &mut contract,
+            base_token_id(),
+            AMOUNT_TO_USE_BIKE + FEE,
+            "0",
+        );
+        contract.return_bike(0, ReturnLocation::Station("other".to_string()));
+        let summary = contract.treasury_summary();
+        assert_eq!(summary.deposits.0, FEE);
+        assert_eq!(summary.revenue.0, revenue + 2 * rental_revenue + FEE);
+    }
+
+    // 使用料を支払わない定期券での使用でも, NEARに換算した手数料を受け取ることを確認
+    #[test]
+    fn charge_rebalancing_fee_for_pass_ride() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = new_contract(1);
+        contract.add_station("other".to_string(), Location { lat: 1, lon: 1 }, 10);
+        contract.set_rebalancing_config(RebalancingConfig {
+            adjustment: RebalancingAdjustment::Fee {
+                amount: U128::from(AMOUNT_TO_USE_BIKE / 2),
+            },
+            relocation_reward: U128::from(0),
+        });
+        ft_transfer_call(
+            &mut context,
+            &mut contract,
+            base_token_id(),
+            DEFAULT_DAY_PASS_PRICE,
+            "buy_pass:day",
+        );
+
+        testing_env!(context
+            .attached_deposit(DEFAULT_NEAR_PRICE_TO_USE_BIKE / 2)
+            .build());
+        contract.rent_with_pass(0);
+        testing_env!(context.attached_deposit(0).build());
+        contract.return_bike(0, ReturnLocation::Station("other".to_string()));
+        assert_eq!(
+            contract.near_revenue().0,
+            DEFAULT_NEAR_PRICE_TO_USE_BIKE / 2
+        );
+        assert!(contract.deposits_of(0).is_empty());
+    }
+
+    // 満車のステーションから空のステーションへバイクを移動した点検者に, 再配置の報酬が加えられることを確認
+    #[test]
+    fn add_relocation_reward_to_inspector() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = new_contract(1);
+        contract.set_inspection_rules(U64::from(0), U64::from(0));
+        contract.add_station("full".to_string(), Location { lat: 1, lon: 1 }, 1);
+        contract.add_station("empty".to_string(), Location { lat: 2, lon: 2 }, 10);
+        contract.set_rebalancing_config(RebalancingConfig {
+            adjustment: RebalancingAdjustment::None,
+            relocation_reward: U128::from(5),
+        });
+        fund_reward_pool(&mut context, &mut contract);
+        ft_transfer_call(
+            &mut context,
+            &mut contract,
+            base_token_id(),
+            AMOUNT_TO_USE_BIKE,
+            "0",
+        );
+        contract.return_bike(0, ReturnLocation::Station("full".to_string()));
+
+        testing_env!(get_context(accounts(2)).build());
+        let reward = contract.amount_reward_for_inspections().0;
+        contract.inspect_bike(0);
+        contract.return_bike(0, ReturnLocation::Station("empty".to_string()));
+        // 点検の報酬と再配置の報酬を合わせて送信します
+        let summary = contract.treasury_summary();
+        assert_eq!(summary.committed_rewards.0, 0);
+        assert_eq!(summary.ft_balance.0, 100 + AMOUNT_TO_USE_BIKE - reward - 5);
+    }
+
+    // 期限を過ぎてから故障を報告して使用を終えた場合も, 返却と同様に評価が下がることを確認
+    #[test]
+    fn overdue_penalty_on_maintenance_report() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.block_timestamp(0).build());
+        let mut contract = new_contract(1);
+        contract.set_reputation_config(ReputationConfig {
+            on_time_reward: 1,
+            overdue_penalty: 5,
+            damage_penalty: 20,
+            min_score: -10,
+            max_ride_duration: U64::from(100),
+        });
+
+        ft_transfer_call(
+            &mut context,
+            &mut contract,
+            base_token_id(),
+            AMOUNT_TO_USE_BIKE,
+            "0",
+        );
+        testing_env!(context.block_timestamp(101).build());
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            contract.start_maintenance(0, None);
+        }));
+        assert!(result.is_err());
+
+        contract.start_maintenance(0, Some(station()));
+        assert!(contract.is_in_maintenance(0));
+        assert_eq!(contract.reputation_of(accounts(1)), -5);
+        assert_eq!(contract.rides_since_inspection(0), 1);
+    }
+
+    // 期限を過ぎた返却と損傷の確定で評価が下がり, 下限を下回るとバイクを使用できないことを確認
+    #[test]
+    fn refuse_account_with_low_reputation() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = new_contract(1);
+        contract.set_reputation_config(ReputationConfig {
+            on_time_reward: 1,
+            overdue_penalty: 5,
+            damage_penalty: 20,
+            min_score: -4,
+            max_ride_duration: U64::from(100),
+        });
+
+        ft_transfer_call(
+            &mut context,
+            &mut contract,
+            base_token_id(),
+            AMOUNT_TO_USE_BIKE,
+            "0",
+        );
+        contract.return_bike(0, station());
+        assert_eq!(contract.reputation_of(accounts(1)), 1);
+
+        ft_transfer_call(
+            &mut context,
+            &mut contract,
+            base_token_id(),
+            AMOUNT_TO_USE_BIKE,
+            "0",
+        );
+        testing_env!(context.block_timestamp(101).build());
+        contract.return_bike(0, station());
+        assert_eq!(contract.reputation_of(accounts(1)), -4);
+        assert!(contract.is_allowed_to_ride(accounts(1)));
+
+        // 損傷が確定した後は, 送信したFTが全て返金されます
+        contract.start_maintenance(0, None);
+        contract.penalize_damage(&accounts(1));
+        contract.finish_maintenance(0);
+        assert!(!contract.is_allowed_to_ride(accounts(1)));
+        let refund = ft_transfer_call(
+            &mut context,
+            &mut contract,
+            base_token_id(),
+            AMOUNT_TO_USE_BIKE,
+            "0",
+        );
+        assert!(matches!(refund, PromiseOrValue::Value(U128(n)) if n == AMOUNT_TO_USE_BIKE));
+        assert!(contract.is_available(0));
+    }
+
+    // 利用禁止のアカウントが点検->パニックを起こすか確認
+    #[test]
+    #[should_panic(expected = "Account is banned")]
+    fn inspect_by_banned_account() {
+        let context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = new_contract(1);
+        contract.ban_account(accounts(1));
+        assert!(contract.is_banned(accounts(1)));
+
+        contract.inspect_bike(0);
+    }
+
+    // ストレージの預り金を預けて登録し, 引き出して登録を解除できることを確認
+    #[test]
+    fn storage_deposit_and_unregister() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new(0);
+        let min = contract.storage_balance_bounds().min.0;
+        assert!(min > 0);
+        assert!(contract.storage_balance_of(accounts(2)).is_none());
+
+        testing_env!(context.attached_deposit(min * 2).build());
+        let balance = contract.storage_deposit(Some(accounts(2)), None);
+        assert_eq!(balance.total.0, min * 2);
+        assert_eq!(balance.available.0, min);
+
+        testing_env!(get_context(accounts(2)).attached_deposit(1).build());
+        let balance = contract.storage_withdraw(Some(U128::from(min)));
+        assert_eq!(balance.available.0, 0);
+        assert!(contract.storage_unregister(None));
+        assert!(contract.storage_balance_of(accounts(2)).is_none());
+    }
+
+    // バイクを使用したアカウントのために使用したストレージが記録されることを確認
+    #[test]
+    fn track_storage_of_rider() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = new_contract(1);
+        let available = contract
+            .storage_balance_of(accounts(1))
+            .unwrap()
+            .available
+            .0;
+
+        ft_transfer_call(
+            &mut context,
+            &mut contract,
+            base_token_id(),
+            AMOUNT_TO_USE_BIKE,
+            "0",
+        );
+        contract.return_bike(0, station());
+        assert!(
+            contract
+                .storage_balance_of(accounts(1))
+                .unwrap()
+                .available
+                .0
+                < available
+        );
+
+        // 記録があるアカウントは登録を解除できません
+        testing_env!(context.attached_deposit(1).build());
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            contract.storage_unregister(None)
+        }));
+        assert!(result.is_err());
+    }
+
+    // 登録したバイクの情報の大きさのストレージが, 所有者の預り金から差し引かれることを確認
+    #[test]
+    fn charge_storage_of_registered_bike() {
+        let context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = new_contract(0);
+        let available = contract
+            .storage_balance_of(accounts(2))
+            .unwrap()
+            .available
+            .0;
+
+        let index = contract.register_bike(U128::from(AMOUNT_TO_USE_BIKE), STATION.to_string());
+        let size = storage_size(&contract.bikes[index]) as Balance;
+        assert_eq!(
+            contract
+                .storage_balance_of(accounts(2))
+                .unwrap()
+                .available
+                .0,
+            available - size * env::storage_byte_cost()
+        );
+    }
+
+    // 預り金を超えてストレージを使用してもバイクを返却でき, 引き出せる預り金が0になることを確認.
+    // 登録されていないアカウントもバイクを返却できることを確認
+    #[test]
+    fn return_bike_beyond_storage_balance() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = new_contract(1);
+        testing_env!(context.attached_deposit(1).build());
+        assert!(contract.storage_unregister(None));
+        let min = contract.storage_balance_bounds().min.0;
+        testing_env!(context.attached_deposit(min).build());
+        contract.storage_deposit(None, None);
+
+        testing_env!(context.attached_deposit(0).build());
+        ft_transfer_call(
+            &mut context,
+            &mut contract,
+            base_token_id(),
+            AMOUNT_TO_USE_BIKE,
+            "0",
+        );
+        contract.return_bike(0, station());
+        assert!(contract.is_available(0));
+        assert_eq!(
+            contract
+                .storage_balance_of(accounts(1))
+                .unwrap()
+                .available
+                .0,
+            0
+        );
+
+        ft_transfer_call(
+            &mut context,
+            &mut contract,
+            base_token_id(),
+            AMOUNT_TO_USE_BIKE,
+            "0",
+        );
+        // 登録されていないアカウントによる返却
+        contract.storage_accounts.remove(&accounts(1));
+        contract.return_bike(0, station());
+        assert!(contract.is_available(0));
+    }
+
+    // 登録されていないアカウントで定期券を購入->パニックを起こすか確認
+    #[test]
+    #[should_panic(expected = "Account is not registered")]
+    fn buy_pass_without_registration() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new(0);
+
+        ft_transfer_call(
+            &mut context,
+            &mut contract,
+            base_token_id(),
+            DEFAULT_DAY_PASS_PRICE,
+            "buy_pass:day",
+        );
+    }
+
+    // 識別情報とステーションを指定してバイクをまとめて追加できることを確認
+    #[test]
+    fn add_bikes_with_metadata() {
+        let context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = new_contract(1);
+        let metadata = BikeMetadata {
+            serial_number: "SN-001".to_string(),
+            model: "city".to_string(),
+        };
+        let results = contract.add_bikes(
+            vec![
+                NewBike {
+                    metadata: metadata.clone(),
+                    station_id: Some(STATION.to_string()),
+                },
+                NewBike {
+                    metadata: BikeMetadata {
+                        serial_number: "SN-002".to_string(),
+                        model: "city".to_string(),
+                    },
+                    station_id: None,
+                },
+            ],
+            10,
+        );
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].index, 1);
+        assert_eq!(results[1].index, 2);
+        assert_eq!(contract.num_of_bikes(), 3);
+        assert_eq!(contract.bike_metadata(1), Some(metadata));
+        // new()で用意したバイク0も同じステーションに置かれ, station_idを指定していないバイク2は空きがあるステーションに置かれます.
+        assert_eq!(
+            contract.available_bikes_at(STATION.to_string()),
+            vec![0, 1, 2]
+        );
+    }
+
+    // 1台でも修理中にできないバイクがある->全て変更せずにパニックを起こすか確認
+    #[test]
+    #[should_panic(expected = "Batch failed: bike 1: Bike is not available")]
+    fn start_maintenance_batch_all_or_nothing() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = new_contract(3);
+        ft_transfer_call(
+            &mut context,
+            &mut contract,
+            base_token_id(),
+            AMOUNT_TO_USE_BIKE,
+            "1",
+        );
+
+        contract.start_maintenance_batch(vec![0, 1, 2], 3);
+    }
+
+    // 使用中と点検中のバイクをまとめて返却できることを確認
+    #[test]
+    fn force_return_bikes() {
+        testing_env!(get_context(accounts(1)).block_timestamp(0).build());
+        let mut contract = new_contract(2);
+        contract.set_inspection_rules(U64::from(0), U64::from(0));
+        let mut context = get_context(accounts(2));
+        fund_reward_pool(&mut context, &mut contract);
+        ft_transfer_call(
+            &mut context,
+            &mut contract,
+            base_token_id(),
+            AMOUNT_TO_USE_BIKE,
+            "0",
+        );
+        testing_env!(get_context(accounts(3)).build());
+        contract.inspect_bike(1);
+
+        testing_env!(get_context(accounts(1)).build());
+        let results = contract.force_return_bikes(vec![0, 1], STATION.to_string(), 2);
+        assert_eq!(results[0].result, format!("returned from {}", accounts(2)));
+        assert_eq!(
+            results[1].result,
+            format!("inspection cancelled for {}", accounts(3))
+        );
+        assert!(contract.is_available(0));
+        assert!(contract.is_available(1));
+        assert_eq!(contract.treasury_summary().committed_rewards.0, 0);
+        assert_eq!(contract.available_bikes_at(STATION.to_string()), vec![0, 1]);
+    }
+
+    // limitを超える台数を指定->パニックを起こすか確認
+    #[test]
+    #[should_panic(expected = "Batch size exceeds the limit")]
+    fn batch_exceeds_limit() {
+        let context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = new_contract(3);
+
+        contract.start_maintenance_batch(vec![0, 1, 2], 2);
+    }
+
+    // 最初のバージョンの状態が, 使用中のバイクを保ったまま移行されることを確認
+    #[test]
+    fn migrate_from_baseline() {
+        let context = get_context(accounts(0));
+        testing_env!(context.build());
+        let baseline = BaselineContract {
+            bikes: vec![
+                BaselineBike::Available,
+                BaselineBike::InUse(accounts(1)),
+                BaselineBike::Inspection(accounts(2)),
+            ],
+        };
+        env::storage_write(b"STATE", &baseline.try_to_vec().unwrap());
+
+        let contract = Contract::migrate();
+        assert_eq!(contract.num_of_bikes(), 3);
+        assert_eq!(contract.who_is_using(1), Some(accounts(1)));
+        assert!(contract.is_available(2));
+        assert!(contract.has_role(accounts(0), Role::Owner));
+        assert_eq!(contract.available_bikes_at(STATION.to_string()), vec![0, 2]);
+    }
+
+    // 現在の形式の状態は, そのまま使用されることを確認
+    #[test]
+    fn migrate_current_state() {
+        let context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = new_contract(2);
+        contract.set_referral_bonus(U128::from(7));
+        env::state_write(&contract);
+
+        let contract = Contract::migrate();
+        assert_eq!(contract.num_of_bikes(), 2);
+        assert_eq!(contract.referral_bonus().0, 7);
+    }
+
+    // 都市の設定で初期化し, 設定した管理者に変更されること, バイクが設定したステーションに順に置かれることを確認
+    #[test]
+    fn new_with_config() {
+        let context = get_context(accounts(0));
+        testing_env!(context.build());
+        let contract = Contract::new_with_config(CityConfig {
+            owner_id: accounts(1),
+            num_of_bikes: 3,
+            price: U128::from(50),
+            near_price: U128::from(1),
+            inspection_interval: U64::from(100),
+            rides_per_inspection: 5,
+            security_deposit_bps: 1000,
+            stations: vec![
+                CityStation {
+                    station_id: STATION.to_string(),
+                    location: Location { lat: 1, lon: 2 },
+                    capacity: 2,
+                },
+                CityStation {
+                    station_id: "south".to_string(),
+                    location: Location { lat: -1, lon: 2 },
+                    capacity: 2,
+                },
+            ],
+        });
+        assert!(contract.has_role(accounts(1), Role::Owner));
+        assert!(!contract.has_role(accounts(0), Role::Owner));
+        assert_eq!(contract.num_of_bikes(), 3);
+        assert_eq!(contract.amount_to_use_bike().0, 50);
+        assert_eq!(contract.inspection_interval().0, 100);
+        assert_eq!(contract.rides_per_inspection(), 5);
+        assert_eq!(contract.security_deposit_bps(), 1000);
+        assert_eq!(contract.stations().len(), 2);
+        assert_eq!(contract.available_bikes_at(STATION.to_string()), vec![0, 1]);
+        assert_eq!(contract.available_bikes_at("south".to_string()), vec![2]);
+    }
+
+    // 初期化したアカウント自身を管理者に設定した場合, Ownerが残ることを確認
+    #[test]
+    fn new_with_config_owned_by_initializer() {
+        let context = get_context(accounts(0));
+        testing_env!(context.build());
+        let contract = Contract::new_with_config(CityConfig {
+            owner_id: accounts(0),
+            num_of_bikes: 1,
+            price: U128::from(50),
+            near_price: U128::from(1),
+            inspection_interval: U64::from(100),
+            rides_per_inspection: 5,
+            security_deposit_bps: 0,
+            stations: vec![CityStation {
+                station_id: STATION.to_string(),
+                location: Location { lat: 1, lon: 2 },
+                capacity: 1,
+            }],
+        });
+        assert!(contract.has_role(accounts(0), Role::Owner));
+    }
+
+    // 複数のロールを付与し, ロールの権限で管理用のメソッドを呼び出せることを確認
+    #[test]
+    fn grant_and_revoke_roles() {
+        let context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = new_contract(1);
+        assert!(contract.has_role(accounts(1), Role::Owner));
+
+        contract.grant_role(accounts(2), Role::Operator);
+        contract.grant_role(accounts(2), Role::Auditor);
+        assert_eq!(
+            contract.roles_of(accounts(2)),
+            vec![Role::Operator, Role::Auditor]
+        );
+        assert!(contract.has_permission(accounts(2), Permission::Pause));
+        assert!(!contract.has_permission(accounts(2), Permission::ManageTreasury));
+
+        testing_env!(get_context(accounts(2)).build());
+        contract.add_station("north".to_string(), Location { lat: 1, lon: 1 }, 5);
+        contract.emergency_stop();
+        assert!(contract.pause_state().rentals);
+
+        testing_env!(get_context(accounts(1)).build());
+        contract.revoke_role(accounts(2), Role::Operator);
+        assert!(!contract.has_role(accounts(2), Role::Operator));
+        assert_eq!(
+            contract.accounts_with_role(Role::Auditor),
+            vec![accounts(2)]
+        );
+    }
+
+    // ManageRolesの権限がないアカウントがロールを付与->パニックを起こすか確認
+    #[test]
+    #[should_panic(expected = "Requires the ManageRoles permission")]
+    fn grant_role_without_permission() {
+        let context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = new_contract(1);
+        contract.grant_role(accounts(2), Role::Operator);
+
+        testing_env!(get_context(accounts(2)).build());
+        contract.grant_role(accounts(3), Role::Operator);
+    }
+
+    // 最後のOwnerのロールを取り消し->パニックを起こすか確認
+    #[test]
+    #[should_panic(expected = "Cannot revoke the last owner")]
+    fn revoke_last_owner() {
+        let context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = new_contract(1);
+
+        contract.revoke_role(accounts(1), Role::Owner);
+    }
+
+    // 評議会の承認を経て提案が実行されることを確認
+    #[test]
+    fn propose_approve_and_execute() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = new_contract(1);
+        contract.set_council(vec![accounts(2), accounts(3), accounts(4)], 2);
+
+        testing_env!(get_context(accounts(2)).build());
+        let args = near_sdk::serde_json::to_vec(&near_sdk::serde_json::json!({ "price": "5" }));
+        let proposal_id = contract.propose(
+            "set_near_price".to_string(),
+            Base64VecU8::from(args.unwrap()),
+            "lower the NEAR price".to_string(),
+        );
+        testing_env!(get_context(accounts(3)).build());
+        contract.approve(proposal_id);
+        contract.execute_proposal(proposal_id);
+        let proposal = contract.get_proposal(proposal_id).unwrap();
+        assert_eq!(proposal.approvals, vec![accounts(2), accounts(3)]);
+        assert_eq!(proposal.status, ProposalStatus::Executing);
+
+        // 提案の実行: このコントラクト自身からの呼び出し
+        testing_env!(get_context(accounts(0)).build());
+        contract.set_near_price(U128::from(5));
+        testing_env_with_promise_result(&mut context, PromiseResult::Successful(vec![]));
+        assert!(contract.callback_execute_proposal(proposal_id));
+        assert_eq!(contract.near_price().0, 5);
+        assert_eq!(
+            contract.get_proposal(proposal_id).unwrap().status,
+            ProposalStatus::Executed
+        );
+    }
+
+    // 評議会を設定した後にOwnerが直接料金を変更->パニックを起こすか確認
+    #[test]
+    #[should_panic(expected = "Requires an approved proposal")]
+    fn governed_method_without_proposal() {
+        let context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = new_contract(1);
+        contract.set_council(vec![accounts(2), accounts(3)], 2);
+
+        contract.set_near_price(U128::from(5));
+    }
+
+    // 重複したメンバーを数えて承認の数を満たす評議会を設定->パニックを起こすか確認
+    #[test]
+    #[should_panic(expected = "Approval threshold exceeds the number of council members")]
+    fn set_council_with_duplicate_members() {
+        let context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = new_contract(1);
+
+        contract.set_council(vec![accounts(2), accounts(2)], 2);
+    }
+
+    // 評議会を設定した後に修理担当者が直接預り金を受け取る->パニックを起こすか確認
+    #[test]
+    #[should_panic(expected = "Requires an approved proposal")]
+    fn claim_deposit_without_proposal() {
+        let context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = new_contract(1);
+        contract.grant_role(accounts(2), Role::Mechanic);
+        contract.set_council(vec![accounts(3), accounts(4)], 2);
+
+        testing_env!(get_context(accounts(2)).build());
+        contract.claim_deposit(0, accounts(1), U128::from(1));
+    }
+
+    // 承認の数が足りない提案を実行->パニックを起こすか確認
+    #[test]
+    #[should_panic(expected = "Not enough approvals")]
+    fn execute_proposal_without_enough_approvals() {
+        let context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = new_contract(1);
+        contract.set_council(vec![accounts(2), accounts(3)], 2);
+
+        testing_env!(get_context(accounts(2)).build());
+        let proposal_id = contract.propose(
+            "withdraw_revenue".to_string(),
+            Base64VecU8::from(b"{}".to_vec()),
+            "".to_string(),
+        );
+        contract.execute_proposal(proposal_id);
+    }
+
+    // DAOに管理を委ねると, 以前のOwnerは管理用のメソッドを呼び出せないことを確認
+    #[test]
+    #[should_panic(expected = "Requires the ManageSettings permission")]
+    fn hand_ownership_to_dao() {
+        let context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = new_contract(1);
+        contract.set_dao(accounts(5));
+        assert_eq!(contract.dao_id(), Some(accounts(5)));
+        assert_eq!(contract.accounts_with_role(Role::Owner), vec![accounts(5)]);
+
+        // DAOのコントラクトの代わりにaccounts(5)から呼び出します.
+        testing_env!(get_context(accounts(5)).build());
+        contract.set_near_price(U128::from(5));
+        assert_eq!(contract.near_price().0, 5);
+
+        testing_env!(get_context(accounts(1)).build());
+        contract.set_near_price(U128::from(1));
     }
 }
@@ -0,0 +1,187 @@
+use near_sdk::{
+    borsh::{self, BorshDeserialize, BorshSerialize},
+    env,
+    json_types::U128,
+    log, near_bindgen,
+    serde::{Deserialize, Serialize},
+    AccountId,
+};
+
+use crate::*;
+
+const MINUTE: u64 = 60 * 1_000_000_000;
+
+// 累計のポイントに応じた会員ランク
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum LoyaltyTier {
+    Member,
+    Bronze,
+    Silver,
+    Gold,
+}
+
+// ポイントの付与と交換, 会員ランクの条件と割引(%)
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct LoyaltyConfig {
+    // 使用料を全額支払ったバイクの使用1分ごとに付与するポイント
+    pub points_per_minute: U128,
+    // 1回の使用で付与するポイントの上限
+    pub max_points_per_ride: U128,
+    // redeem_points()で無料でバイクを使用するのに必要なポイント
+    pub free_ride_points: U128,
+    // 各ランクになるのに必要な累計のポイント
+    pub bronze_points: U128,
+    pub silver_points: U128,
+    pub gold_points: U128,
+    // 各ランクでftの使用料から割り引く割合(%)
+    pub bronze_discount: u32,
+    pub silver_discount: u32,
+    pub gold_discount: u32,
+}
+
+impl LoyaltyConfig {
+    fn tier(&self, lifetime_points: u128) -> LoyaltyTier {
+        if lifetime_points >= self.gold_points.0 {
+            LoyaltyTier::Gold
+        } else if lifetime_points >= self.silver_points.0 {
+            LoyaltyTier::Silver
+        } else if lifetime_points >= self.bronze_points.0 {
+            LoyaltyTier::Bronze
+        } else {
+            LoyaltyTier::Member
+        }
+    }
+
+    fn discount(&self, tier: LoyaltyTier) -> u32 {
+        match tier {
+            LoyaltyTier::Member => 0,
+            LoyaltyTier::Bronze => self.bronze_discount,
+            LoyaltyTier::Silver => self.silver_discount,
+            LoyaltyTier::Gold => self.gold_discount,
+        }
+    }
+}
+
+// アカウントごとのポイント
+#[derive(BorshDeserialize, BorshSerialize, Default)]
+pub struct LoyaltyAccount {
+    // 交換に使用できるポイント
+    points: u128,
+    // これまでに付与されたポイントの合計. 会員ランクの判定に使用します.
+    lifetime_points: u128,
+}
+
+// loyalty_of()の返り値
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct LoyaltyView {
+    pub points: U128,
+    pub lifetime_points: U128,
+    pub tier: LoyaltyTier,
+}
+
+#[near_bindgen]
+impl Contract {
+    pub fn loyalty_config(&self) -> LoyaltyConfig {
+        self.loyalty_config.clone()
+    }
+
+    pub fn loyalty_of(&self, account_id: AccountId) -> LoyaltyView {
+        let account = self.loyalty_accounts.get(&account_id).unwrap_or_default();
+        LoyaltyView {
+            points: U128::from(account.points),
+            lifetime_points: U128::from(account.lifetime_points),
+            tier: self.loyalty_config.tier(account.lifetime_points),
+        }
+    }
+
+    // ポイントの付与と交換, 会員ランクの条件と割引を設定します. ManageSettingsの権限が必要です.
+    pub fn set_loyalty_config(&mut self, config: LoyaltyConfig) {
+        self.assert_governed(Permission::ManageSettings);
+        assert!(
+            config.bronze_points.0 <= config.silver_points.0
+                && config.silver_points.0 <= config.gold_points.0,
+            "Tier thresholds must be in ascending order"
+        );
+        assert!(
+            config.bronze_discount <= 100
+                && config.silver_discount <= 100
+                && config.gold_discount <= 100,
+            "Discount must not exceed 100 percent"
+        );
+        log!(
+            "set loyalty config: {} points per minute, {} points per free ride",
+            config.points_per_minute.0,
+            config.free_ride_points.0
+        );
+        self.loyalty_config = config;
+    }
+
+    // バイク 使用可 -> 使用中
+    // ポイントを交換して, 使用料を支払わずにバイクを使用します.
+    // 所有者がいるバイクには使用できません. 預り金はNEARで添付します.
+    #[payable]
+    pub fn redeem_points(&mut self, index: usize) {
+        let predecessor_id = env::predecessor_account_id();
+        let initial_storage = env::storage_usage();
+        assert!(!self.pause_state.rentals, "Rentals are paused");
+        assert!(
+            self.bikes[index].owner_id.is_none(),
+            "Points cannot be redeemed for a bike owned by an account"
+        );
+        let mut account = self
+            .loyalty_accounts
+            .get(&predecessor_id)
+            .unwrap_or_default();
+        let points = self.loyalty_config.free_ride_points.0;
+        assert!(account.points >= points, "Not enough loyalty points");
+        account.points -= points;
+        self.loyalty_accounts.insert(&predecessor_id, &account);
+        self.charge_storage(&predecessor_id, initial_storage);
+
+        log!("{} redeems {} points for a ride", &predecessor_id, points);
+        self.hold_attached_deposit(index, &predecessor_id);
+        self.use_bike(index, predecessor_id.clone());
+    }
+}
+
+impl Contract {
+    // バイクの使用を終えた際に, 使用時間に応じたポイントを付与します.
+    // 使用料を支払った使用のみが対象で, ポイントは割引後に支払った使用料の割合に応じて減らします.
+    pub(crate) fn add_loyalty_points(
+        &mut self,
+        account_id: &AccountId,
+        ride_time: u64,
+        payment: &Payment,
+    ) {
+        if payment.amount == 0 || payment.price == 0 {
+            return;
+        }
+        let points =
+            (ride_time / MINUTE) as u128 * self.loyalty_config.points_per_minute.0 * payment.amount
+                / payment.price;
+        let points = points.min(self.loyalty_config.max_points_per_ride.0);
+        if points == 0 {
+            return;
+        }
+        log!("add {} loyalty points to {}", points, account_id);
+        let initial_storage = env::storage_usage();
+        let mut account = self.loyalty_accounts.get(account_id).unwrap_or_default();
+        account.points += points;
+        account.lifetime_points += points;
+        self.loyalty_accounts.insert(account_id, &account);
+        self.track_storage(account_id, initial_storage);
+    }
+
+    // 会員ランクによる割引. ftの使用料から割り引く量を返します.
+    pub(crate) fn loyalty_discount(&self, account_id: &AccountId, price: u128) -> u128 {
+        let lifetime_points = self
+            .loyalty_accounts
+            .get(account_id)
+            .map_or(0, |account| account.lifetime_points);
+        let tier = self.loyalty_config.tier(lifetime_points);
+        price * self.loyalty_config.discount(tier) as u128 / 100
+    }
+}
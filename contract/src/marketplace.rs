@@ -0,0 +1,225 @@
+use near_sdk::{
+    borsh::{self, BorshDeserialize, BorshSerialize},
+    env,
+    json_types::U128,
+    log, near_bindgen,
+    serde::{Deserialize, Serialize},
+    serde_json, AccountId, Promise, PromiseResult,
+};
+
+use crate::*;
+
+// 手数料の上限(ベーシスポイント, 10000 = 100%)
+const MAX_PLATFORM_FEE_BPS: u32 = 10000;
+
+// バイクの所有者が設定できる料金の範囲と, コントラクトが受け取る手数料
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct MarketplaceConfig {
+    pub min_price: U128,
+    pub max_price: U128,
+    // 使用料のうちコントラクトが受け取る割合(ベーシスポイント). 残りはバイクの所有者が受け取ります.
+    pub platform_fee_bps: u32,
+    // 所有者ごとと全体で登録できるバイクの台数. 登録を解除したバイクも含みます.
+    // バイクの一覧はコントラクトの状態と共に読み込まれるため, 増えすぎないよう制限します.
+    pub max_listings_per_owner: u32,
+    pub max_listings: u32,
+}
+
+#[near_bindgen]
+impl Contract {
+    pub fn marketplace_config(&self) -> MarketplaceConfig {
+        self.marketplace_config.clone()
+    }
+
+    // バイクの所有者. コントラクトが所有するバイクの場合はNone
+    pub fn bike_owner(&self, index: usize) -> Option<AccountId> {
+        self.bikes[index].owner_id.clone()
+    }
+
+    // FT_CONTRACT_ACCOUNTでバイクを使用するのに必要なft
+    pub fn bike_price(&self, index: usize) -> U128 {
+        U128::from(self.rental_price(index, &base_token_id()))
+    }
+
+    // バイクの所有者が受け取れる使用料
+    pub fn earnings_of(&self, account_id: AccountId) -> U128 {
+        U128::from(self.owner_earnings.get(&account_id).unwrap_or(0))
+    }
+
+    // 料金の範囲と手数料を設定します. ManageSettingsの権限が必要です.
+    pub fn set_marketplace_config(&mut self, config: MarketplaceConfig) {
+        self.assert_governed(Permission::ManageSettings);
+        assert!(
+            config.min_price.0 <= config.max_price.0,
+            "min_price must not exceed max_price"
+        );
+        assert!(
+            config.platform_fee_bps <= MAX_PLATFORM_FEE_BPS,
+            "Platform fee must not exceed {} basis points",
+            MAX_PLATFORM_FEE_BPS
+        );
+        log!(
+            "set marketplace config: price {}-{}, platform fee {}",
+            config.min_price.0,
+            config.max_price.0,
+            config.platform_fee_bps
+        );
+        self.marketplace_config = config;
+    }
+
+    // 呼び出したアカウントが所有するバイクをステーションに置いて登録し, 登録したバイクのindexを返します.
+    // バイクの情報はコントラクトの状態に含まれるため, その大きさのストレージを所有者が負担します.
+    pub fn register_bike(&mut self, price: U128, station_id: String) -> usize {
+        let predecessor_id = env::predecessor_account_id();
+        self.assert_price_in_range(price.0);
+        let (owner_listings, listings) =
+            self.bikes
+                .iter()
+                .fold((0, 0), |(owned, all), bike| match &bike.owner_id {
+                    Some(owner_id) if *owner_id == predecessor_id => (owned + 1, all + 1),
+                    Some(_) => (owned, all + 1),
+                    None => (owned, all),
+                });
+        assert!(
+            owner_listings < self.marketplace_config.max_listings_per_owner,
+            "Listing limit per owner reached"
+        );
+        assert!(
+            listings < self.marketplace_config.max_listings,
+            "Listing limit reached"
+        );
+        assert!(self.stations.get(&station_id).is_some(), "Unknown station");
+        self.assert_station_has_capacity(&station_id);
+        let index = self.bikes.len();
+        log!(
+            "{} registers bike {}: {} ft",
+            &predecessor_id,
+            index,
+            price.0
+        );
+        let mut info = BikeInfo::new(Some(predecessor_id.clone()), price.0);
+        info.station_id = Some(station_id);
+        self.charge_storage_bytes(&predecessor_id, storage_size(&info));
+        self.bikes.push(info);
+        index
+    }
+
+    // バイクの料金を変更します. バイクの所有者のみ呼び出せます.
+    pub fn set_bike_price(&mut self, index: usize, price: U128) {
+        self.assert_bike_owner(index);
+        self.assert_price_in_range(price.0);
+        log!("set price of bike {}: {} ft", index, price.0);
+        self.bikes[index].price = price.0;
+    }
+
+    // バイク 使用可 -> 登録解除
+    // バイクの所有者のみ呼び出せます.
+    pub fn delist_bike(&mut self, index: usize) {
+        self.assert_bike_owner(index);
+        log!("delist bike {}", index);
+        match &self.bikes[index].state {
+            Bike::Available => self.set_state(index, Bike::Delisted),
+            _ => panic!("Bike is not available"),
+        }
+    }
+
+    // バイク 登録解除 -> 使用可
+    // バイクの所有者のみ呼び出せます.
+    pub fn relist_bike(&mut self, index: usize) {
+        self.assert_bike_owner(index);
+        self.assert_price_in_range(self.bikes[index].price);
+        log!("relist bike {}", index);
+        match &self.bikes[index].state {
+            Bike::Delisted => self.set_state(index, Bike::Available),
+            _ => panic!("Bike is not delisted"),
+        }
+    }
+
+    // バイクの所有者が受け取れる使用料を全て送信します.
+    pub fn claim_earnings(&mut self) -> Promise {
+        let predecessor_id = env::predecessor_account_id();
+        let initial_storage = env::storage_usage();
+        let amount = self
+            .owner_earnings
+            .remove(&predecessor_id)
+            .expect("No earnings to claim");
+        self.track_storage(&predecessor_id, initial_storage);
+        log!("{} claims earnings: {}", &predecessor_id, amount);
+        self.transfer_from_treasury(
+            TreasuryAccount::OwnerEarnings,
+            predecessor_id.clone(),
+            amount,
+        )
+        .then(
+            Self::ext(env::current_account_id())
+                .with_static_gas(GAS_FOR_CALLBACK)
+                .callback_claim_earnings(predecessor_id, U128::from(amount)),
+        )
+    }
+
+    // callback
+    // 送信が失敗した場合, バイクの所有者が受け取れる使用料を元に戻します.
+    // 直前のcallback_refund_treasury()の返り値(送信が成功したか)を受け取ります.
+    #[private]
+    pub fn callback_claim_earnings(&mut self, owner_id: AccountId, amount: U128) {
+        assert_eq!(env::promise_results_count(), 1, "This is a callback method");
+        let transferred = match env::promise_result(0) {
+            PromiseResult::NotReady => unreachable!(),
+            PromiseResult::Failed => false,
+            PromiseResult::Successful(value) => {
+                serde_json::from_slice::<bool>(&value).unwrap_or(false)
+            }
+        };
+        if !transferred {
+            log!("refund earnings to {}: {}", &owner_id, amount.0);
+            self.add_earnings(&owner_id, amount.0);
+        }
+    }
+}
+
+impl Contract {
+    fn assert_bike_owner(&self, index: usize) {
+        assert_eq!(
+            self.bikes[index].owner_id.as_ref(),
+            Some(&env::predecessor_account_id()),
+            "Only the bike owner can call this method"
+        );
+    }
+
+    fn assert_price_in_range(&self, price: u128) {
+        assert!(
+            self.marketplace_config.min_price.0 <= price
+                && price <= self.marketplace_config.max_price.0,
+            "Price must be between {} and {}",
+            self.marketplace_config.min_price.0,
+            self.marketplace_config.max_price.0
+        );
+    }
+
+    fn add_earnings(&mut self, owner_id: &AccountId, amount: u128) {
+        let initial_storage = env::storage_usage();
+        let earnings = self.owner_earnings.get(owner_id).unwrap_or(0);
+        self.owner_earnings.insert(owner_id, &(earnings + amount));
+        self.track_storage(owner_id, initial_storage);
+    }
+
+    // 使用料を受け取ります.
+    // 所有者がいるバイクの場合, 手数料を差し引いた分を所有者が受け取れる使用料として記録し,
+    // 手数料のみを使用料の分配の割合に従って分配します.
+    pub(crate) fn receive_rental_fee(&mut self, index: usize, fee: u128) {
+        let platform_fee = match self.bikes[index].owner_id.clone() {
+            None => fee,
+            Some(owner_id) => {
+                let platform_fee = fee * self.marketplace_config.platform_fee_bps as u128
+                    / MAX_PLATFORM_FEE_BPS as u128;
+                self.treasury
+                    .receive(TreasuryAccount::OwnerEarnings, fee - platform_fee);
+                self.add_earnings(&owner_id, fee - platform_fee);
+                platform_fee
+            }
+        };
+        self.treasury
+            .receive_rental_fee(platform_fee, &self.revenue_share);
+    }
+}
@@ -0,0 +1,54 @@
+use near_sdk::{
+    borsh::{self, BorshDeserialize, BorshSerialize},
+    env, log, near_bindgen, AccountId,
+};
+
+use crate::*;
+
+// near_bindgenがコントラクトの状態を保存するキー
+const STATE_KEY: &[u8] = b"STATE";
+
+// 最初のバージョンのバイクの状態
+#[derive(BorshDeserialize, BorshSerialize)]
+pub enum BaselineBike {
+    Available,
+    InUse(AccountId),
+    Inspection(AccountId),
+}
+
+// 最初のバージョンのコントラクトの状態. バイクの状態のみを保存していました.
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct BaselineContract {
+    pub bikes: Vec<BaselineBike>,
+}
+
+#[near_bindgen]
+impl Contract {
+    // コードを置き換えた後に, 保存されている状態を現在の形式に移行します. upgrade()がコードの置き換えに続けて呼び出します.
+    // 現在の形式の状態はそのまま使用し, 最初のバージョンの形式の状態はnew()と同じ設定で移行します.
+    #[private]
+    #[init(ignore_state)]
+    pub fn migrate() -> Self {
+        let state = env::storage_read(STATE_KEY).expect("Contract is not initialized");
+        if let Ok(contract) = Self::try_from_slice(&state) {
+            log!("state is up to date");
+            return contract;
+        }
+        let baseline =
+            BaselineContract::try_from_slice(&state).expect("Unknown contract state layout");
+        log!("migrate {} bikes from baseline state", baseline.bikes.len());
+        let mut this = Self::new(baseline.bikes.len());
+        for (index, bike) in baseline.bikes.into_iter().enumerate() {
+            match bike {
+                BaselineBike::Available => {}
+                // 使用料の支払いの記録がないため, 返却時の精算はありません.
+                BaselineBike::InUse(user) => this.set_state(index, Bike::InUse(user)),
+                // 報酬を取り分けていないため, 点検は取り消して使用可能とします.
+                BaselineBike::Inspection(inspector) => {
+                    log!("cancel inspection of bike {} by {}", index, inspector)
+                }
+            }
+        }
+        this
+    }
+}
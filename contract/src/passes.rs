@@ -0,0 +1,178 @@
+use near_sdk::{
+    borsh::{self, BorshDeserialize, BorshSerialize},
+    env,
+    json_types::{U128, U64},
+    log, near_bindgen,
+    serde::{Deserialize, Serialize},
+    AccountId,
+};
+
+use crate::*;
+
+const DAY: u64 = 24 * 60 * 60 * 1_000_000_000;
+
+// 定期券の種類
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Copy, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum PassKind {
+    Day,
+    Week,
+    Month,
+}
+
+impl PassKind {
+    // ft_on_transferのmsg("buy_pass:day"など)が定期券の購入を表す場合, 定期券の種類を返します.
+    pub(crate) fn from_buy_pass_msg(msg: &str) -> Option<Self> {
+        match msg {
+            "buy_pass:day" => Some(Self::Day),
+            "buy_pass:week" => Some(Self::Week),
+            "buy_pass:month" => Some(Self::Month),
+            _ => None,
+        }
+    }
+
+    fn duration(&self) -> u64 {
+        match self {
+            Self::Day => DAY,
+            Self::Week => 7 * DAY,
+            Self::Month => 30 * DAY,
+        }
+    }
+}
+
+// 定期券の料金(FT_CONTRACT_ACCOUNT)と, 定期券で1日に使用できる回数
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PassConfig {
+    pub day_price: U128,
+    pub week_price: U128,
+    pub month_price: U128,
+    pub daily_ride_cap: u32,
+}
+
+impl PassConfig {
+    fn price(&self, kind: PassKind) -> u128 {
+        match kind {
+            PassKind::Day => self.day_price.0,
+            PassKind::Week => self.week_price.0,
+            PassKind::Month => self.month_price.0,
+        }
+    }
+}
+
+// アカウントごとの定期券
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct Pass {
+    // 有効期限(ナノ秒)
+    expires_at: u64,
+    // 最後に定期券でバイクを使用した日(block_timestamp / 1日)
+    last_ride_day: u64,
+    // last_ride_dayに定期券でバイクを使用した回数
+    rides_on_last_ride_day: u32,
+}
+
+// pass_of()の返り値
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PassView {
+    pub expires_at: U64,
+    pub rides_today: u32,
+}
+
+#[near_bindgen]
+impl Contract {
+    pub fn pass_config(&self) -> PassConfig {
+        self.pass_config.clone()
+    }
+
+    // アカウントの定期券. 定期券を購入していない場合はNone
+    pub fn pass_of(&self, account_id: AccountId) -> Option<PassView> {
+        self.passes.get(&account_id).map(|pass| PassView {
+            expires_at: U64::from(pass.expires_at),
+            rides_today: if pass.last_ride_day == today() {
+                pass.rides_on_last_ride_day
+            } else {
+                0
+            },
+        })
+    }
+
+    pub fn has_active_pass(&self, account_id: AccountId) -> bool {
+        self.passes
+            .get(&account_id)
+            .is_some_and(|pass| pass.expires_at > env::block_timestamp())
+    }
+
+    // 定期券の料金と1日に使用できる回数を設定します. ManageSettingsの権限が必要です.
+    pub fn set_pass_config(&mut self, config: PassConfig) {
+        self.assert_governed(Permission::ManageSettings);
+        log!(
+            "set pass config: day {}, week {}, month {}, daily ride cap {}",
+            config.day_price.0,
+            config.week_price.0,
+            config.month_price.0,
+            config.daily_ride_cap
+        );
+        self.pass_config = config;
+    }
+
+    // バイク 使用可 -> 使用中
+    // 有効な定期券を持っている場合, 1日に使用できる回数まで使用料を支払わずにバイクを使用できます.
+    // 所有者がいるバイクには使用できません. 預り金はNEARで添付します.
+    #[payable]
+    pub fn rent_with_pass(&mut self, index: usize) {
+        let predecessor_id = env::predecessor_account_id();
+        let initial_storage = env::storage_usage();
+        assert!(!self.pause_state.rentals, "Rentals are paused");
+        assert!(
+            self.bikes[index].owner_id.is_none(),
+            "Pass cannot be used for a bike owned by an account"
+        );
+        let mut pass = self
+            .passes
+            .get(&predecessor_id)
+            .filter(|pass| pass.expires_at > env::block_timestamp())
+            .expect("No active pass");
+        if pass.last_ride_day != today() {
+            pass.last_ride_day = today();
+            pass.rides_on_last_ride_day = 0;
+        }
+        assert!(
+            pass.rides_on_last_ride_day < self.pass_config.daily_ride_cap,
+            "Daily ride cap of the pass is reached"
+        );
+        pass.rides_on_last_ride_day += 1;
+        self.passes.insert(&predecessor_id, &pass);
+        self.charge_storage(&predecessor_id, initial_storage);
+
+        log!("{} rents bike with pass", &predecessor_id);
+        self.hold_attached_deposit(index, &predecessor_id);
+        self.use_bike(index, predecessor_id.clone());
+    }
+}
+
+fn today() -> u64 {
+    env::block_timestamp() / DAY
+}
+
+impl Contract {
+    // 定期券を購入します. 有効な定期券を持っている場合は有効期限を延長します.
+    pub(crate) fn buy_pass(&mut self, account_id: AccountId, kind: PassKind, amount: u128) {
+        let price = self.pass_config.price(kind);
+        assert_eq!(amount, price, "Require {} ft to buy the pass", price);
+        log!("{} buys {:?} pass", &account_id, kind);
+
+        let now = env::block_timestamp();
+        let initial_storage = env::storage_usage();
+        let mut pass = self.passes.get(&account_id).unwrap_or(Pass {
+            expires_at: now,
+            last_ride_day: 0,
+            rides_on_last_ride_day: 0,
+        });
+        pass.expires_at = pass.expires_at.max(now) + kind.duration();
+        self.passes.insert(&account_id, &pass);
+        self.charge_storage(&account_id, initial_storage);
+        self.treasury
+            .receive_rental_fee(amount, &self.revenue_share);
+    }
+}
@@ -0,0 +1,183 @@
+use near_sdk::{
+    borsh::{self, BorshDeserialize, BorshSerialize},
+    env,
+    json_types::{Base64VecU8, U128, U64},
+    log, near_bindgen,
+    serde::{Deserialize, Serialize},
+    AccountId, CryptoHash,
+};
+
+use crate::*;
+
+// プロモーションの割引
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum Discount {
+    Percent(u32), // 使用料のうち割り引く割合(%)
+    Fixed(U128), // 割り引くFT_CONTRACT_ACCOUNTのトークンの量. 使用料を超える場合は使用料の全額を割り引きます.
+}
+
+impl Discount {
+    fn apply(&self, token_id: &AccountId, price: u128) -> u128 {
+        match self {
+            Self::Percent(percent) => price * *percent as u128 / 100,
+            Self::Fixed(amount) => {
+                // トークンごとに単位が異なるため, 他のトークンでの支払いには使用できません.
+                assert_eq!(
+                    *token_id,
+                    base_token_id(),
+                    "Fixed discount applies only to {}",
+                    FT_CONTRACT_ACCOUNT
+                );
+                amount.0.min(price)
+            }
+        }
+    }
+}
+
+// プロモーション. プロモーションコードはハッシュ(sha256)のみを保存します.
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct PromoCampaign {
+    code_hash: CryptoHash,
+    discount: Discount,
+    // プロモーションコードを使用できる回数の合計
+    max_uses: u32,
+    // 1つのアカウントがプロモーションコードを使用できる回数
+    max_uses_per_account: u32,
+    // 有効期限(ナノ秒)
+    expires_at: u64,
+    // プロモーションコードが使用された回数
+    uses: u32,
+}
+
+// promo_campaign()の返り値
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PromoCampaignView {
+    pub discount: Discount,
+    pub max_uses: u32,
+    pub max_uses_per_account: u32,
+    pub expires_at: U64,
+    pub uses: u32,
+}
+
+#[near_bindgen]
+impl Contract {
+    pub fn promo_campaign(&self, campaign_id: String) -> Option<PromoCampaignView> {
+        self.promo_campaigns
+            .get(&campaign_id)
+            .map(|campaign| PromoCampaignView {
+                discount: campaign.discount,
+                max_uses: campaign.max_uses,
+                max_uses_per_account: campaign.max_uses_per_account,
+                expires_at: U64::from(campaign.expires_at),
+                uses: campaign.uses,
+            })
+    }
+
+    pub fn promo_campaigns(&self) -> Vec<String> {
+        self.promo_campaigns.keys().collect()
+    }
+
+    // account_idがプロモーションコードを使用した回数
+    pub fn promo_uses_of(&self, campaign_id: String, account_id: AccountId) -> u32 {
+        self.promo_uses.get(&(campaign_id, account_id)).unwrap_or(0)
+    }
+
+    // プロモーションを作成します. ManagePromotionsの権限が必要です.
+    // code_hashにはプロモーションコードのsha256ハッシュを指定します.
+    pub fn create_promo_campaign(
+        &mut self,
+        campaign_id: String,
+        code_hash: Base64VecU8,
+        discount: Discount,
+        max_uses: u32,
+        max_uses_per_account: u32,
+        expires_at: U64,
+    ) {
+        self.assert_governed(Permission::ManagePromotions);
+        let code_hash: CryptoHash = code_hash
+            .0
+            .try_into()
+            .unwrap_or_else(|_| panic!("Code hash must be 32 bytes"));
+        if let Discount::Percent(percent) = discount {
+            assert!(
+                0 < percent && percent <= 100,
+                "Discount percent must be between 1 and 100"
+            );
+        }
+        assert!(
+            self.promo_campaigns.get(&campaign_id).is_none(),
+            "Promo campaign already exists"
+        );
+        assert!(
+            self.promo_codes.get(&code_hash).is_none(),
+            "Promo code is already used by another campaign"
+        );
+        log!("create promo campaign {}: {:?}", &campaign_id, discount);
+        self.promo_codes.insert(&code_hash, &campaign_id);
+        self.promo_campaigns.insert(
+            &campaign_id,
+            &PromoCampaign {
+                code_hash,
+                discount,
+                max_uses,
+                max_uses_per_account,
+                expires_at: expires_at.0,
+                uses: 0,
+            },
+        );
+    }
+
+    // プロモーションを削除します. ManagePromotionsの権限が必要です.
+    pub fn remove_promo_campaign(&mut self, campaign_id: String) {
+        self.assert_permission(Permission::ManagePromotions);
+        let campaign = self
+            .promo_campaigns
+            .remove(&campaign_id)
+            .expect("Promo campaign not found");
+        log!("remove promo campaign {}", &campaign_id);
+        self.promo_codes.remove(&campaign.code_hash);
+    }
+}
+
+impl Contract {
+    // プロモーションコードを使用し, token_idで支払う使用料から割り引く量を返します.
+    pub(crate) fn redeem_promo_code(
+        &mut self,
+        code: &str,
+        account_id: AccountId,
+        token_id: &AccountId,
+        price: u128,
+    ) -> u128 {
+        let code_hash: CryptoHash = env::sha256(code.as_bytes()).try_into().unwrap();
+        let campaign_id = self
+            .promo_codes
+            .get(&code_hash)
+            .expect("Promo code is not valid");
+        let mut campaign = self.promo_campaigns.get(&campaign_id).unwrap();
+        assert!(
+            env::block_timestamp() < campaign.expires_at,
+            "Promo code is expired"
+        );
+        assert!(
+            campaign.uses < campaign.max_uses,
+            "Promo campaign is fully used"
+        );
+        let key = (campaign_id, account_id);
+        let uses = self.promo_uses.get(&key).unwrap_or(0);
+        assert!(
+            uses < campaign.max_uses_per_account,
+            "Promo code usage limit reached for the account"
+        );
+
+        let discount = campaign.discount.apply(token_id, price);
+        log!("{} redeems promo campaign {}: {}", &key.1, &key.0, discount);
+        campaign.uses += 1;
+        self.promo_campaigns.insert(&key.0, &campaign);
+        let initial_storage = env::storage_usage();
+        self.promo_uses.insert(&key, &(uses + 1));
+        self.charge_storage(&key.1, initial_storage);
+        discount
+    }
+}
@@ -0,0 +1,206 @@
+use near_sdk::{
+    borsh::{self, BorshDeserialize, BorshSerialize},
+    env,
+    json_types::U128,
+    log, near_bindgen,
+    serde::{Deserialize, Serialize},
+    serde_json, AccountId, Promise, PromiseResult,
+};
+
+use crate::*;
+
+// 紹介されたアカウントごとの紹介
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct Referral {
+    referrer_id: AccountId,
+    // 紹介者に支払った報酬. 紹介されたアカウントがまだバイクの使用を終えていない場合はNone
+    reward: Option<u128>,
+}
+
+// 紹介者ごとの紹介の記録
+#[derive(BorshDeserialize, BorshSerialize, Default)]
+pub struct ReferrerInfo {
+    referees: Vec<AccountId>,
+    // 受け取れる報酬
+    unclaimed_rewards: u128,
+    // これまでに支払われた報酬の合計
+    total_rewards: u128,
+}
+
+// referral_of()の返り値
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ReferralView {
+    pub referrer_id: AccountId,
+    pub reward: Option<U128>,
+}
+
+// referrer_info()の返り値
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ReferrerInfoView {
+    pub referees: Vec<AccountId>,
+    pub unclaimed_rewards: U128,
+    pub total_rewards: U128,
+}
+
+#[near_bindgen]
+impl Contract {
+    // 紹介されたアカウントが初めてバイクの使用を終えた際に, 紹介者に支払う報酬
+    pub fn referral_bonus(&self) -> U128 {
+        U128::from(self.referral_bonus)
+    }
+
+    // account_idを紹介したアカウントと, 紹介者に支払った報酬
+    pub fn referral_of(&self, account_id: AccountId) -> Option<ReferralView> {
+        self.referrals
+            .get(&account_id)
+            .map(|referral| ReferralView {
+                referrer_id: referral.referrer_id,
+                reward: referral.reward.map(U128::from),
+            })
+    }
+
+    // 紹介者が紹介したアカウントと, 紹介の報酬
+    pub fn referrer_info(&self, referrer_id: AccountId) -> ReferrerInfoView {
+        let info = self.referrers.get(&referrer_id).unwrap_or_default();
+        ReferrerInfoView {
+            referees: info.referees,
+            unclaimed_rewards: U128::from(info.unclaimed_rewards),
+            total_rewards: U128::from(info.total_rewards),
+        }
+    }
+
+    // 紹介の報酬を設定します. ManageSettingsの権限が必要です.
+    pub fn set_referral_bonus(&mut self, bonus: U128) {
+        self.assert_governed(Permission::ManageSettings);
+        log!("set referral bonus: {}", bonus.0);
+        self.referral_bonus = bonus.0;
+    }
+
+    // 紹介者が受け取れる紹介の報酬を全て送信します.
+    pub fn claim_referral_rewards(&mut self) -> Promise {
+        let predecessor_id = env::predecessor_account_id();
+        let mut info = self.referrers.get(&predecessor_id).unwrap_or_default();
+        let amount = info.unclaimed_rewards;
+        assert!(amount > 0, "No referral rewards to claim");
+        log!("{} claims referral rewards: {}", &predecessor_id, amount);
+        info.unclaimed_rewards = 0;
+        self.referrers.insert(&predecessor_id, &info);
+        self.transfer_from_treasury(
+            TreasuryAccount::ReferralRewards,
+            predecessor_id.clone(),
+            amount,
+        )
+        .then(
+            Self::ext(env::current_account_id())
+                .with_static_gas(GAS_FOR_CALLBACK)
+                .callback_claim_referral_rewards(predecessor_id, U128::from(amount)),
+        )
+    }
+
+    // callback
+    // 送信が失敗した場合, 紹介者が受け取れる報酬を元に戻します.
+    // 直前のcallback_refund_treasury()の返り値(送信が成功したか)を受け取ります.
+    #[private]
+    pub fn callback_claim_referral_rewards(&mut self, referrer_id: AccountId, amount: U128) {
+        assert_eq!(env::promise_results_count(), 1, "This is a callback method");
+        let transferred = match env::promise_result(0) {
+            PromiseResult::NotReady => unreachable!(),
+            PromiseResult::Failed => false,
+            PromiseResult::Successful(value) => {
+                serde_json::from_slice::<bool>(&value).unwrap_or(false)
+            }
+        };
+        if !transferred {
+            log!("refund referral rewards to {}: {}", &referrer_id, amount.0);
+            let mut info = self.referrers.get(&referrer_id).unwrap_or_default();
+            info.unclaimed_rewards += amount.0;
+            self.referrers.insert(&referrer_id, &info);
+        }
+    }
+}
+
+impl Contract {
+    // 新規ユーザを紹介したアカウントを記録します.
+    // 紹介は新規ユーザ自身のみ記録でき, すでにバイクを使用したアカウントは紹介されたものとして扱いません.
+    pub(crate) fn add_referral(&mut self, new_user_id: &AccountId, referrer_id: AccountId) {
+        self.assert_new_user(new_user_id);
+        assert_ne!(*new_user_id, referrer_id, "Account cannot refer itself");
+        assert!(
+            self.referrals.get(new_user_id).is_none(),
+            "Account is already referred"
+        );
+        log!("{} refers {}", &referrer_id, new_user_id);
+        let initial_storage = env::storage_usage();
+        self.referrals.insert(
+            new_user_id,
+            &Referral {
+                referrer_id: referrer_id.clone(),
+                reward: None,
+            },
+        );
+        self.charge_storage(new_user_id, initial_storage);
+        let initial_storage = env::storage_usage();
+        let mut info = self.referrers.get(&referrer_id).unwrap_or_default();
+        info.referees.push(new_user_id.clone());
+        self.referrers.insert(&referrer_id, &info);
+        self.track_storage(&referrer_id, initial_storage);
+    }
+
+    // 新規ユーザ自身による呼び出しで, 新規ユーザであることを確認します.
+    pub(crate) fn assert_new_user(&self, new_user_id: &AccountId) {
+        assert_eq!(
+            env::predecessor_account_id(),
+            *new_user_id,
+            "Only the new user can call this method"
+        );
+        assert!(self.is_new_user(new_user_id), "Account is not a new user");
+    }
+
+    // バイクの使用を終えたことがなく, アカウントのためのストレージを使用していないアカウントか
+    fn is_new_user(&self, account_id: &AccountId) -> bool {
+        self.reputations.get(account_id).is_none()
+            && self.loyalty_accounts.get(account_id).is_none()
+            && self
+                .storage_accounts
+                .get(account_id)
+                .is_none_or(|account| account.used_bytes == 0)
+    }
+
+    // 紹介されたアカウントが初めてバイクの使用を終えた際に, 紹介者の報酬を新規ユーザへの送信のための資金から取り分けます.
+    // 資金が足りない場合は報酬を支払わず, 次にバイクの使用を終えた際に改めて支払います.
+    pub(crate) fn reward_referrer(&mut self, referee_id: &AccountId) {
+        let mut referral = match self.referrals.get(referee_id) {
+            Some(referral) if referral.reward.is_none() => referral,
+            _ => return,
+        };
+        let bonus = self.referral_bonus;
+        if self.treasury.balance(TreasuryAccount::GiftBudget) < bonus {
+            log!("Not enough GiftBudget for the referral bonus");
+            return;
+        }
+        log!(
+            "reward {} for referring {}: {}",
+            &referral.referrer_id,
+            referee_id,
+            bonus
+        );
+        self.treasury.withdraw(TreasuryAccount::GiftBudget, bonus);
+        self.treasury
+            .deposit(TreasuryAccount::ReferralRewards, bonus);
+        let initial_storage = env::storage_usage();
+        let mut info = self
+            .referrers
+            .get(&referral.referrer_id)
+            .unwrap_or_default();
+        info.unclaimed_rewards += bonus;
+        info.total_rewards += bonus;
+        self.referrers.insert(&referral.referrer_id, &info);
+        self.track_storage(&referral.referrer_id, initial_storage);
+        let initial_storage = env::storage_usage();
+        referral.reward = Some(bonus);
+        self.referrals.insert(referee_id, &referral);
+        self.track_storage(referee_id, initial_storage);
+    }
+}
@@ -0,0 +1,113 @@
+use near_sdk::{
+    borsh::{self, BorshDeserialize, BorshSerialize},
+    json_types::U64,
+    log, near_bindgen,
+    serde::{Deserialize, Serialize},
+    AccountId,
+};
+
+use crate::*;
+
+// 評価の増減と, バイクの使用や点検ができる評価の下限
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ReputationConfig {
+    // 期限内に返却した場合に加える評価
+    pub on_time_reward: i32,
+    // 期限を過ぎて返却した場合に差し引く評価
+    pub overdue_penalty: i32,
+    // バイクの損傷が確定した場合に差し引く評価
+    pub damage_penalty: i32,
+    // 評価がこれを下回るアカウントはバイクの使用や点検ができません.
+    pub min_score: i32,
+    // バイクを借りてから返却するまでの期限(ナノ秒)
+    pub max_ride_duration: U64,
+}
+
+#[near_bindgen]
+impl Contract {
+    pub fn reputation_config(&self) -> ReputationConfig {
+        self.reputation_config.clone()
+    }
+
+    // アカウントの評価. 初期値は0です.
+    pub fn reputation_of(&self, account_id: AccountId) -> i32 {
+        self.reputations.get(&account_id).unwrap_or(0)
+    }
+
+    pub fn is_banned(&self, account_id: AccountId) -> bool {
+        self.banned_accounts.contains(&account_id)
+    }
+
+    pub fn banned_accounts(&self) -> Vec<AccountId> {
+        self.banned_accounts.to_vec()
+    }
+
+    // アカウントがバイクの使用や点検をできるか
+    pub fn is_allowed_to_ride(&self, account_id: AccountId) -> bool {
+        !self.banned_accounts.contains(&account_id)
+            && self.reputation_of(account_id) >= self.reputation_config.min_score
+    }
+
+    // 評価の増減と下限を設定します. ManageSettingsの権限が必要です.
+    pub fn set_reputation_config(&mut self, config: ReputationConfig) {
+        self.assert_permission(Permission::ManageSettings);
+        log!(
+            "set reputation config: on time {}, overdue {}, damage {}, min score {}",
+            config.on_time_reward,
+            config.overdue_penalty,
+            config.damage_penalty,
+            config.min_score
+        );
+        self.reputation_config = config;
+    }
+
+    // アカウントを利用禁止にします. ManageAccountsの権限が必要です.
+    pub fn ban_account(&mut self, account_id: AccountId) {
+        self.assert_permission(Permission::ManageAccounts);
+        log!("ban {}", &account_id);
+        self.banned_accounts.insert(&account_id);
+    }
+
+    pub fn unban_account(&mut self, account_id: AccountId) {
+        self.assert_permission(Permission::ManageAccounts);
+        log!("unban {}", &account_id);
+        self.banned_accounts.remove(&account_id);
+    }
+}
+
+impl Contract {
+    pub(crate) fn assert_allowed_to_ride(&self, account_id: &AccountId) {
+        assert!(
+            !self.banned_accounts.contains(account_id),
+            "Account is banned"
+        );
+        assert!(
+            self.reputation_of(account_id.clone()) >= self.reputation_config.min_score,
+            "Reputation is too low"
+        );
+    }
+
+    // バイクの使用を終えた際に, 期限内に返却したかに応じて評価を更新します.
+    pub(crate) fn update_reputation_on_return(&mut self, account_id: &AccountId, ride_time: u64) {
+        if ride_time <= self.reputation_config.max_ride_duration.0 {
+            self.add_reputation(account_id, self.reputation_config.on_time_reward);
+        } else {
+            log!("{} returned bike late", account_id);
+            self.add_reputation(account_id, -self.reputation_config.overdue_penalty);
+        }
+    }
+
+    // バイクの損傷が確定した際に評価を差し引きます.
+    pub(crate) fn penalize_damage(&mut self, account_id: &AccountId) {
+        log!("{} is penalized for damage", account_id);
+        self.add_reputation(account_id, -self.reputation_config.damage_penalty);
+    }
+
+    fn add_reputation(&mut self, account_id: &AccountId, delta: i32) {
+        let initial_storage = env::storage_usage();
+        let score = self.reputation_of(account_id.clone()).saturating_add(delta);
+        self.reputations.insert(account_id, &score);
+        self.track_storage(account_id, initial_storage);
+    }
+}
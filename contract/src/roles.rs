@@ -0,0 +1,181 @@
+use near_sdk::{
+    borsh::{self, BorshDeserialize, BorshSerialize},
+    env, near_bindgen,
+    serde::{Deserialize, Serialize},
+    serde_json::json,
+    AccountId,
+};
+
+use crate::*;
+
+// 管理用のメソッドを呼び出すための権限
+#[derive(
+    BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Copy, PartialEq, Debug,
+)]
+#[serde(crate = "near_sdk::serde")]
+pub enum Permission {
+    ManageRoles,      // ロールの付与と取り消し
+    ManageSettings,   // 料金や点検の規則などの設定
+    ManageTreasury,   // 使用料の引き出しと資金の配分
+    ManageFleet,      // バイクの追加や修理, 強制返却, ステーションの管理
+    ManagePromotions, // プロモーションの作成と削除
+    ManageAccounts,   // アカウントの利用禁止と解除
+    Pause,            // 機能の一時停止
+    Maintain,         // 修理の完了
+    ClaimDeposits,    // 修理費用の預り金からの受け取り
+    Inspect,          // 点検(open_inspectionがfalseの場合)
+    ResolveDisputes,  // 異議申し立ての解決
+    Audit,            // 資金の残高の照合
+    Upgrade,          // コントラクトのアップグレード
+}
+
+const ALL_PERMISSIONS: &[Permission] = &[
+    Permission::ManageRoles,
+    Permission::ManageSettings,
+    Permission::ManageTreasury,
+    Permission::ManageFleet,
+    Permission::ManagePromotions,
+    Permission::ManageAccounts,
+    Permission::Pause,
+    Permission::Maintain,
+    Permission::ClaimDeposits,
+    Permission::Inspect,
+    Permission::ResolveDisputes,
+    Permission::Audit,
+    Permission::Upgrade,
+];
+
+// アカウントに付与するロール. アカウントは複数のロールを持つことができます.
+#[derive(
+    BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Copy, PartialEq, Debug,
+)]
+#[serde(crate = "near_sdk::serde")]
+pub enum Role {
+    Owner,    // 全ての権限
+    Operator, // バイクとステーション, プロモーション, アカウントの管理と一時停止
+    Mechanic, // 修理の完了と修理費用の受け取り
+    Inspector,
+    Auditor,
+    Arbiter, // 異議申し立ての解決
+}
+
+impl Role {
+    fn permissions(&self) -> &'static [Permission] {
+        match self {
+            Role::Owner => ALL_PERMISSIONS,
+            Role::Operator => &[
+                Permission::ManageFleet,
+                Permission::ManagePromotions,
+                Permission::ManageAccounts,
+                Permission::Pause,
+            ],
+            Role::Mechanic => &[Permission::Maintain, Permission::ClaimDeposits],
+            Role::Inspector => &[Permission::Inspect],
+            Role::Auditor => &[Permission::Audit],
+            Role::Arbiter => &[Permission::ResolveDisputes],
+        }
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    pub fn has_role(&self, account_id: AccountId, role: Role) -> bool {
+        self.roles_of(account_id).contains(&role)
+    }
+
+    pub fn roles_of(&self, account_id: AccountId) -> Vec<Role> {
+        self.roles.get(&account_id).unwrap_or_default()
+    }
+
+    pub fn accounts_with_role(&self, role: Role) -> Vec<AccountId> {
+        self.roles
+            .iter()
+            .filter(|(_, roles)| roles.contains(&role))
+            .map(|(account_id, _)| account_id)
+            .collect()
+    }
+
+    pub fn role_permissions(&self, role: Role) -> Vec<Permission> {
+        role.permissions().to_vec()
+    }
+
+    // アカウントが持つロールのいずれかに権限が含まれるか
+    pub fn has_permission(&self, account_id: AccountId, permission: Permission) -> bool {
+        self.roles_of(account_id)
+            .iter()
+            .any(|role| role.permissions().contains(&permission))
+    }
+
+    // アカウントにロールを付与します. ManageRolesの権限が必要です.
+    pub fn grant_role(&mut self, account_id: AccountId, role: Role) {
+        self.assert_governed(Permission::ManageRoles);
+        if self.add_role(&account_id, role) {
+            emit_event(
+                "role_granted",
+                json!({
+                    "account_id": account_id,
+                    "role": role,
+                    "granted_by": env::predecessor_account_id(),
+                }),
+            );
+        }
+    }
+
+    // アカウントのロールを取り消します. ManageRolesの権限が必要です.
+    // 最後のOwnerのロールは取り消せません.
+    pub fn revoke_role(&mut self, account_id: AccountId, role: Role) {
+        self.assert_governed(Permission::ManageRoles);
+        if role == Role::Owner {
+            assert!(
+                self.accounts_with_role(Role::Owner) != vec![account_id.clone()],
+                "Cannot revoke the last owner"
+            );
+        }
+        if self.remove_role(&account_id, role) {
+            emit_event(
+                "role_revoked",
+                json!({
+                    "account_id": account_id,
+                    "role": role,
+                    "revoked_by": env::predecessor_account_id(),
+                }),
+            );
+        }
+    }
+}
+
+impl Contract {
+    pub(crate) fn assert_permission(&self, permission: Permission) {
+        assert!(
+            self.has_permission(env::predecessor_account_id(), permission),
+            "Requires the {:?} permission",
+            permission
+        );
+    }
+
+    // ロールを付与し, 新たに付与した場合はtrueを返します.
+    pub(crate) fn add_role(&mut self, account_id: &AccountId, role: Role) -> bool {
+        let mut roles = self.roles.get(account_id).unwrap_or_default();
+        if roles.contains(&role) {
+            return false;
+        }
+        roles.push(role);
+        self.roles.insert(account_id, &roles);
+        true
+    }
+
+    // ロールを取り消し, 取り消した場合はtrueを返します.
+    pub(crate) fn remove_role(&mut self, account_id: &AccountId, role: Role) -> bool {
+        let mut roles = self.roles.get(account_id).unwrap_or_default();
+        if !roles.contains(&role) {
+            return false;
+        }
+        roles.retain(|r| *r != role);
+        if roles.is_empty() {
+            self.roles.remove(account_id);
+        } else {
+            self.roles.insert(account_id, &roles);
+        }
+        true
+    }
+}
@@ -0,0 +1,262 @@
+use near_sdk::{
+    borsh::{self, BorshDeserialize, BorshSerialize},
+    json_types::U128,
+    log, near_bindgen,
+    serde::{Deserialize, Serialize},
+    AccountId,
+};
+
+use crate::*;
+
+// 緯度と経度. ファクトリーコントラクトと共有します.
+pub use city_config::Location;
+
+// バイクを返却した場所. ステーションのidもしくは緯度と経度で指定します.
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub enum ReturnLocation {
+    Station(String),
+    Coordinates(Location),
+}
+
+// 借りたステーションと異なるステーションへ返却した場合の精算
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum RebalancingAdjustment {
+    None,
+    // 手数料(FT_CONTRACT_ACCOUNTの量). 使用時に預り金に加えて受け取り, 同じステーションへ返却した場合は預り金と同様に引き出せます.
+    Fee { amount: U128 },
+    // 点検の報酬の資金から支払うFT. 預り金と同様に引き出せます.
+    Bonus { amount: U128 },
+}
+
+// バイクの再配置の設定
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct RebalancingConfig {
+    pub adjustment: RebalancingAdjustment,
+    // 満車のステーションから空のステーションへ点検したバイクを移動した点検者に, 点検の報酬に加えて支払うFT
+    pub relocation_reward: U128,
+}
+
+// ステーション
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct Station {
+    location: Location,
+    // 置くことができるバイクの台数
+    pub(crate) capacity: u32,
+}
+
+// stations()の返り値
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct StationView {
+    pub station_id: String,
+    pub location: Location,
+    pub capacity: u32,
+    pub num_of_bikes: u32,
+}
+
+#[near_bindgen]
+impl Contract {
+    pub fn stations(&self) -> Vec<StationView> {
+        self.stations
+            .iter()
+            .map(|(station_id, station)| StationView {
+                num_of_bikes: self.num_of_bikes_at(&station_id),
+                station_id,
+                location: station.location,
+                capacity: station.capacity,
+            })
+            .collect()
+    }
+
+    // バイクが置かれている, もしくは最後に返却されたステーション. ステーションに置かれていなければNone
+    pub fn bike_station(&self, index: usize) -> Option<String> {
+        self.bikes[index].station_id.clone()
+    }
+
+    // ステーションにある使用可能なバイクのindex
+    pub fn available_bikes_at(&self, station_id: String) -> Vec<usize> {
+        self.bikes
+            .iter()
+            .enumerate()
+            .filter(|(_, bike)| {
+                matches!(bike.state, Bike::Available)
+                    && bike.station_id.as_ref() == Some(&station_id)
+            })
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    pub fn rebalancing_config(&self) -> RebalancingConfig {
+        self.rebalancing_config.clone()
+    }
+
+    // バイクの再配置の設定を変更します. ManageSettingsの権限が必要です.
+    pub fn set_rebalancing_config(&mut self, config: RebalancingConfig) {
+        self.assert_governed(Permission::ManageSettings);
+        log!(
+            "set rebalancing config: {:?}, relocation reward {}",
+            config.adjustment,
+            config.relocation_reward.0
+        );
+        self.rebalancing_config = config;
+    }
+
+    // ステーションを登録, もしくは場所と台数を変更します. ManageFleetの権限が必要です.
+    pub fn add_station(&mut self, station_id: String, location: Location, capacity: u32) {
+        self.assert_permission(Permission::ManageFleet);
+        if let Some(other) = self.station_locations.get(&location) {
+            assert_eq!(
+                other, station_id,
+                "Location is already used by another station"
+            );
+        }
+        if let Some(station) = self.stations.get(&station_id) {
+            self.station_locations.remove(&station.location);
+        }
+        log!(
+            "add station {}: ({}, {}), capacity {}",
+            &station_id,
+            location.lat,
+            location.lon,
+            capacity
+        );
+        self.station_locations.insert(&location, &station_id);
+        self.stations
+            .insert(&station_id, &Station { location, capacity });
+    }
+
+    // バイクが置かれていないステーションを削除します. ManageFleetの権限が必要です.
+    pub fn remove_station(&mut self, station_id: String) {
+        self.assert_permission(Permission::ManageFleet);
+        assert!(
+            self.bikes
+                .iter()
+                .all(|bike| bike.station_id.as_ref() != Some(&station_id)),
+            "Station still has bikes"
+        );
+        let station = self.stations.remove(&station_id).expect("Unknown station");
+        log!("remove station {}", &station_id);
+        self.station_locations.remove(&station.location);
+    }
+}
+
+impl Contract {
+    // 返却した場所のステーションを返します. 登録されていない場所の場合はpanicします.
+    pub(crate) fn resolve_station(&self, location: ReturnLocation) -> String {
+        match location {
+            ReturnLocation::Station(station_id) => {
+                assert!(self.stations.get(&station_id).is_some(), "Unknown station");
+                station_id
+            }
+            ReturnLocation::Coordinates(location) => self
+                .station_locations
+                .get(&location)
+                .expect("Unknown location"),
+        }
+    }
+
+    pub(crate) fn assert_station_has_capacity(&self, station_id: &str) {
+        let station = self.stations.get(&station_id.to_string()).unwrap();
+        assert!(
+            self.num_of_bikes_at(station_id) < station.capacity,
+            "Station is full"
+        );
+    }
+
+    // 借りたステーションと異なるステーションへ返却した場合, 預り金から手数料を受け取るかボーナスを支払います.
+    pub(crate) fn settle_rebalancing(
+        &mut self,
+        index: usize,
+        renter_id: &AccountId,
+        pickup_station_id: Option<&str>,
+        station_id: &str,
+    ) {
+        if pickup_station_id.is_none_or(|pickup| pickup == station_id) {
+            return;
+        }
+        match self.rebalancing_config.adjustment.clone() {
+            RebalancingAdjustment::None => {}
+            RebalancingAdjustment::Fee { .. } => {
+                let fee = self.charge_rebalancing_fee(index, renter_id);
+                log!("charge rebalancing fee to {}: {}", renter_id, fee);
+            }
+            RebalancingAdjustment::Bonus { amount } => {
+                if self.treasury.balance(TreasuryAccount::RewardPool) < amount.0 {
+                    log!("Not enough RewardPool for the rebalancing bonus");
+                    return;
+                }
+                log!("pay rebalancing bonus to {}: {}", renter_id, amount.0);
+                self.treasury
+                    .withdraw(TreasuryAccount::RewardPool, amount.0);
+                self.treasury.deposit(TreasuryAccount::Deposits, amount.0);
+                self.add_deposit_balance(
+                    renter_id.clone(),
+                    Currency::Ft(base_token_id()),
+                    amount.0,
+                );
+            }
+        }
+    }
+
+    // 満車のステーションから空のステーションへ点検したバイクを移動した場合, 点検の報酬に再配置の報酬を加えます.
+    pub(crate) fn add_relocation_reward(
+        &mut self,
+        index: usize,
+        pickup_station_id: Option<&str>,
+        station_id: &str,
+    ) {
+        let pickup_station_id = match pickup_station_id {
+            Some(pickup) if pickup != station_id => pickup,
+            _ => return,
+        };
+        let pickup_station = match self.stations.get(&pickup_station_id.to_string()) {
+            Some(station) => station,
+            None => return,
+        };
+        // 点検中のバイクはステーションの台数に含まないため, 1台を加えて満車だったかを確認します.
+        let was_full = self.num_of_bikes_at(pickup_station_id) + 1 >= pickup_station.capacity;
+        let reward = self.rebalancing_config.relocation_reward.0;
+        if !was_full || self.num_of_bikes_at(station_id) > 0 || reward == 0 {
+            return;
+        }
+        if self.treasury.balance(TreasuryAccount::RewardPool) < reward {
+            log!("Not enough RewardPool for the relocation reward");
+            return;
+        }
+        if let Some(pending_reward) = self.bikes[index].pending_reward.as_mut() {
+            log!("add relocation reward: {}", reward);
+            self.treasury.commit_reward(reward);
+            *pending_reward += reward;
+        }
+    }
+
+    // ステーションに置かれていないバイクを, 空きがあるステーションに順に置きます.
+    pub(crate) fn place_bikes(&mut self) {
+        for index in 0..self.bikes.len() {
+            if self.bikes[index].station_id.is_some() {
+                continue;
+            }
+            let station_id = self
+                .stations
+                .iter()
+                .find(|(station_id, station)| self.num_of_bikes_at(station_id) < station.capacity)
+                .map(|(station_id, _)| station_id)
+                .expect("Stations cannot hold all bikes");
+            self.bikes[index].station_id = Some(station_id);
+        }
+    }
+
+    // ステーションに置かれているバイクの台数. 使用中や点検中のバイクは含みません.
+    pub(crate) fn num_of_bikes_at(&self, station_id: &str) -> u32 {
+        self.bikes
+            .iter()
+            .filter(|bike| {
+                !matches!(bike.state, Bike::InUse(_) | Bike::Inspection(_))
+                    && bike.station_id.as_deref() == Some(station_id)
+            })
+            .count() as u32
+    }
+}
@@ -0,0 +1,247 @@
+use near_sdk::{
+    assert_one_yocto,
+    borsh::{self, BorshDeserialize, BorshSerialize},
+    env,
+    json_types::U128,
+    log, near_bindgen,
+    serde::{Deserialize, Serialize},
+    AccountId, Balance, Promise, StorageUsage,
+};
+
+use crate::*;
+
+// アカウントごとのストレージの預り金と, アカウントのために使用しているストレージ
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct StorageAccount {
+    pub(crate) balance: Balance,
+    // 登録に必要な分を除いた, アカウントのために使用しているストレージ(バイト)
+    pub(crate) used_bytes: StorageUsage,
+}
+
+// NEP-145のstorage_balance_of()などの返り値
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct StorageBalance {
+    pub total: U128,
+    pub available: U128,
+}
+
+// NEP-145のstorage_balance_bounds()の返り値
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct StorageBalanceBounds {
+    pub min: U128,
+    pub max: Option<U128>,
+}
+
+#[near_bindgen]
+impl Contract {
+    // account_idのストレージの預り金を受け取ります. account_idを省略した場合は呼び出したアカウントです.
+    // registration_onlyがtrueの場合, 登録に必要な分を超えて添付されたNEARは返金します.
+    #[payable]
+    pub fn storage_deposit(
+        &mut self,
+        account_id: Option<AccountId>,
+        registration_only: Option<bool>,
+    ) -> StorageBalance {
+        let amount = env::attached_deposit();
+        let account_id = account_id.unwrap_or_else(env::predecessor_account_id);
+        let min_balance = self.storage_balance_bounds().min.0;
+        let mut account = match self.storage_accounts.get(&account_id) {
+            Some(account) => {
+                if registration_only.unwrap_or(false) {
+                    log!("{} is already registered", &account_id);
+                    if amount > 0 {
+                        Promise::new(env::predecessor_account_id()).transfer(amount);
+                    }
+                    return self.storage_balance_of(account_id).unwrap();
+                }
+                account
+            }
+            None => {
+                assert!(
+                    amount >= min_balance,
+                    "Require {} yoctoNEAR to register the account",
+                    min_balance
+                );
+                StorageAccount {
+                    balance: 0,
+                    used_bytes: 0,
+                }
+            }
+        };
+        let deposit = if registration_only.unwrap_or(false) {
+            let refund = amount - min_balance;
+            if refund > 0 {
+                Promise::new(env::predecessor_account_id()).transfer(refund);
+            }
+            min_balance
+        } else {
+            amount
+        };
+        log!("storage deposit of {}: {}", &account_id, deposit);
+        account.balance += deposit;
+        self.storage_accounts.insert(&account_id, &account);
+        self.storage_balance_of(account_id).unwrap()
+    }
+
+    // 使用していないストレージの預り金を引き出します. amountを省略した場合は全て引き出します.
+    #[payable]
+    pub fn storage_withdraw(&mut self, amount: Option<U128>) -> StorageBalance {
+        assert_one_yocto();
+        let predecessor_id = env::predecessor_account_id();
+        let mut account = self
+            .storage_accounts
+            .get(&predecessor_id)
+            .expect("Account is not registered");
+        let available = account
+            .balance
+            .saturating_sub(self.locked_storage_balance(&account));
+        let amount = amount.map_or(available, |amount| amount.0);
+        assert!(amount <= available, "Not enough available storage balance");
+        log!("storage withdraw of {}: {}", &predecessor_id, amount);
+        account.balance -= amount;
+        self.storage_accounts.insert(&predecessor_id, &account);
+        if amount > 0 {
+            Promise::new(predecessor_id.clone()).transfer(amount);
+        }
+        self.storage_balance_of(predecessor_id).unwrap()
+    }
+
+    // 登録を解除し, ストレージの預り金を全て返金します.
+    // アカウントのためにストレージを使用している場合は解除できません. forceには対応していません.
+    #[payable]
+    pub fn storage_unregister(&mut self, force: Option<bool>) -> bool {
+        assert_one_yocto();
+        let predecessor_id = env::predecessor_account_id();
+        let account = match self.storage_accounts.get(&predecessor_id) {
+            Some(account) => account,
+            None => return false,
+        };
+        assert!(
+            account.used_bytes == 0,
+            "Cannot unregister an account with records{}",
+            if force.unwrap_or(false) {
+                ", force is not supported"
+            } else {
+                ""
+            }
+        );
+        log!("unregister {}", &predecessor_id);
+        self.storage_accounts.remove(&predecessor_id);
+        Promise::new(predecessor_id).transfer(account.balance);
+        true
+    }
+
+    pub fn storage_balance_bounds(&self) -> StorageBalanceBounds {
+        StorageBalanceBounds {
+            min: U128::from(self.account_storage_usage as Balance * env::storage_byte_cost()),
+            max: None,
+        }
+    }
+
+    pub fn storage_balance_of(&self, account_id: AccountId) -> Option<StorageBalance> {
+        self.storage_accounts
+            .get(&account_id)
+            .map(|account| StorageBalance {
+                total: U128::from(account.balance),
+                // 返却などで預り金を超えてストレージを使用している場合は0です.
+                available: U128::from(
+                    account
+                        .balance
+                        .saturating_sub(self.locked_storage_balance(&account)),
+                ),
+            })
+    }
+}
+
+impl Contract {
+    // 登録に必要なストレージを, 最も長いAccountIdで登録した場合の増加量から計算します.
+    pub(crate) fn measure_account_storage_usage(&mut self) {
+        let initial_storage = env::storage_usage();
+        let account_id: AccountId = "a".repeat(64).parse().unwrap();
+        self.storage_accounts.insert(
+            &account_id,
+            &StorageAccount {
+                balance: 0,
+                used_bytes: 0,
+            },
+        );
+        self.account_storage_usage = env::storage_usage() - initial_storage;
+        self.storage_accounts.remove(&account_id);
+    }
+
+    // アカウントの記録を作成する操作の前に, 登録されていることを確認します.
+    pub(crate) fn assert_registered(&self, account_id: &AccountId) {
+        assert!(
+            self.storage_accounts.get(account_id).is_some(),
+            "Account is not registered"
+        );
+    }
+
+    // 登録とアカウントのために使用しているストレージの分, 引き出せない預り金
+    fn locked_storage_balance(&self, account: &StorageAccount) -> Balance {
+        (self.account_storage_usage + account.used_bytes) as Balance * env::storage_byte_cost()
+    }
+
+    // initial_storageからのストレージの増減を, 記録を所有するaccount_idのために使用しているストレージとして記録します.
+    // バイクの返却などを妨げないようpanicせず, 登録されていないアカウントの分はコントラクトが負担します.
+    pub(crate) fn track_storage(&mut self, account_id: &AccountId, initial_storage: StorageUsage) {
+        let current_storage = env::storage_usage();
+        if current_storage == initial_storage {
+            return;
+        }
+        let mut account = match self.storage_accounts.get(account_id) {
+            Some(account) => account,
+            None => return,
+        };
+        if current_storage > initial_storage {
+            account.used_bytes += current_storage - initial_storage;
+        } else {
+            account.used_bytes = account
+                .used_bytes
+                .saturating_sub(initial_storage - current_storage);
+        }
+        self.storage_accounts.insert(account_id, &account);
+    }
+
+    // 1つのキーに複数のアカウントの記録をまとめて保存している場合に, 記録のborshでの大きさを
+    // account_idのために使用しているストレージとして記録します. charge_storage()と同様に預り金が足りているかを確認します.
+    pub(crate) fn charge_storage_bytes(&mut self, account_id: &AccountId, bytes: StorageUsage) {
+        self.assert_registered(account_id);
+        let mut account = self.storage_accounts.get(account_id).unwrap();
+        account.used_bytes += bytes;
+        assert!(
+            account.balance >= self.locked_storage_balance(&account),
+            "Not enough storage deposit"
+        );
+        self.storage_accounts.insert(account_id, &account);
+    }
+
+    // charge_storage_bytes()で記録したストレージを解放します. track_storage()と同様にpanicしません.
+    pub(crate) fn release_storage_bytes(&mut self, account_id: &AccountId, bytes: StorageUsage) {
+        if let Some(mut account) = self.storage_accounts.get(account_id) {
+            account.used_bytes = account.used_bytes.saturating_sub(bytes);
+            self.storage_accounts.insert(account_id, &account);
+        }
+    }
+
+    // track_storage()に加えて, アカウントが登録されていてストレージの預り金が足りているかを確認します.
+    pub(crate) fn charge_storage(&mut self, account_id: &AccountId, initial_storage: StorageUsage) {
+        if env::storage_usage() > initial_storage {
+            self.assert_registered(account_id);
+        }
+        self.track_storage(account_id, initial_storage);
+        if let Some(account) = self.storage_accounts.get(account_id) {
+            assert!(
+                account.balance >= self.locked_storage_balance(&account),
+                "Not enough storage deposit"
+            );
+        }
+    }
+}
+
+// 記録をborshで保存した際の大きさ(バイト)
+pub(crate) fn storage_size<T: BorshSerialize>(value: &T) -> StorageUsage {
+    value.try_to_vec().unwrap().len() as StorageUsage
+}
@@ -0,0 +1,237 @@
+use near_sdk::{
+    borsh::{self, BorshDeserialize, BorshSerialize},
+    env,
+    json_types::U128,
+    log, near_bindgen,
+    serde::{Deserialize, Serialize},
+    AccountId, Promise, PromiseResult,
+};
+
+use crate::*;
+
+// 使用料の支払いに使用された通貨
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum Currency {
+    Ft(AccountId), // AccountIdのftコントラクトのトークン
+    Near,          // NEAR(yoctoNEAR)
+}
+
+// バイクの使用料の支払い. 返金や精算は支払いに使用された通貨で行います.
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct Payment {
+    pub currency: Currency,
+    // 割引後に支払われた使用料
+    pub amount: u128,
+    // 割引前の使用料
+    pub price: u128,
+}
+
+#[near_bindgen]
+impl Contract {
+    // 使用料の支払いに使用できるトークンと, コントラクトが所有するバイクの使用料
+    pub fn accepted_tokens(&self) -> Vec<(AccountId, U128)> {
+        self.accepted_tokens
+            .iter()
+            .map(|(token_id, price)| (token_id, U128::from(price)))
+            .collect()
+    }
+
+    pub fn token_price(&self, token_id: AccountId) -> Option<U128> {
+        self.accepted_tokens.get(&token_id).map(U128::from)
+    }
+
+    // バイクの使用に支払われた通貨. 使用中でない場合はNone
+    pub fn payment_currency_of(&self, index: usize) -> Option<Currency> {
+        self.bikes[index]
+            .payment
+            .as_ref()
+            .map(|payment| payment.currency.clone())
+    }
+
+    // NEARでバイクを使用するのに必要なyoctoNEAR
+    pub fn near_price(&self) -> U128 {
+        U128::from(self.near_price)
+    }
+
+    // NEARで受け取った使用料(yoctoNEAR)
+    pub fn near_revenue(&self) -> U128 {
+        U128::from(self.near_revenue)
+    }
+
+    // NEARでバイクを使用するのに必要なyoctoNEARを設定します. ManageSettingsの権限が必要です.
+    pub fn set_near_price(&mut self, price: U128) {
+        self.assert_governed(Permission::ManageSettings);
+        log!("set near price: {}", price.0);
+        self.near_price = price.0;
+    }
+
+    // バイク 使用可 -> 使用中
+    // ftを持っていないユーザのために, NEARで使用料を支払ってバイクを使用します.
+    // 使用料と預り金を超えて添付されたNEARは返金します.
+    #[payable]
+    pub fn rent_with_near(&mut self, index: usize) {
+        let deposit = env::attached_deposit();
+        assert!(!self.pause_state.rentals, "Rentals are paused");
+        assert!(
+            self.bikes[index].owner_id.is_none(),
+            "Bike owned by an account accepts only {}",
+            FT_CONTRACT_ACCOUNT
+        );
+        // 使用料に加えて預り金が必要です.
+        let security_deposit = self.rental_deposit(&Currency::Near, self.near_price);
+        let required = self.near_price + security_deposit;
+        assert!(
+            deposit >= required,
+            "Require {} yoctoNEAR to use the bike",
+            required
+        );
+        log!(
+            "{} rents bike with {} yoctoNEAR",
+            env::predecessor_account_id(),
+            deposit
+        );
+
+        self.use_bike(index, env::predecessor_account_id());
+        // 返金や精算に使用するため, 支払いに使用された通貨を記録します.
+        self.bikes[index].payment = Some(Payment {
+            currency: Currency::Near,
+            amount: self.near_price,
+            price: self.near_price,
+        });
+        self.hold_deposit(
+            index,
+            env::predecessor_account_id(),
+            Currency::Near,
+            security_deposit,
+        );
+        if deposit > required {
+            Promise::new(env::predecessor_account_id()).transfer(deposit - required);
+        }
+    }
+
+    // NEARで受け取った使用料をreceiver_idへ送信します. ManageTreasuryの権限が必要です.
+    pub fn withdraw_near_revenue(&mut self, receiver_id: AccountId, amount: U128) -> Promise {
+        self.assert_governed(Permission::ManageTreasury);
+        assert!(self.near_revenue >= amount.0, "Not enough NEAR revenue");
+        log!("withdraw NEAR revenue to {}: {}", &receiver_id, amount.0);
+        self.near_revenue -= amount.0;
+        Promise::new(receiver_id).transfer(amount.0)
+    }
+
+    // FT_CONTRACT_ACCOUNT以外のトークンで受け取った使用料
+    pub fn token_revenue_of(&self, token_id: AccountId) -> U128 {
+        U128::from(self.token_revenue.get(&token_id).unwrap_or(0))
+    }
+
+    // 使用料の支払いに使用できるトークンを追加, もしくは使用料を変更します. ManageSettingsの権限が必要です.
+    pub fn add_accepted_token(&mut self, token_id: AccountId, price: U128) {
+        self.assert_governed(Permission::ManageSettings);
+        log!("accept token {}: {} to use the bike", &token_id, price.0);
+        self.accepted_tokens.insert(&token_id, &price.0);
+    }
+
+    // ManageSettingsの権限が必要です. FT_CONTRACT_ACCOUNTは削除できません.
+    pub fn remove_accepted_token(&mut self, token_id: AccountId) {
+        self.assert_governed(Permission::ManageSettings);
+        assert_ne!(token_id, base_token_id(), "Cannot remove the base token");
+        log!("remove accepted token {}", &token_id);
+        self.accepted_tokens.remove(&token_id);
+    }
+
+    // FT_CONTRACT_ACCOUNT以外のトークンで受け取った使用料をreceiver_idへ送信します. ManageTreasuryの権限が必要です.
+    pub fn withdraw_token_revenue(
+        &mut self,
+        token_id: AccountId,
+        receiver_id: AccountId,
+        amount: U128,
+    ) -> Promise {
+        self.assert_governed(Permission::ManageTreasury);
+        let revenue = self.token_revenue.get(&token_id).unwrap_or(0);
+        assert!(revenue >= amount.0, "Not enough revenue of {}", &token_id);
+        log!(
+            "withdraw revenue of {} to {}: {}",
+            &token_id,
+            &receiver_id,
+            amount.0
+        );
+        let transfer = self.cross_contract_call_transfer(
+            &token_id,
+            receiver_id.to_string(),
+            amount.0.to_string(),
+        );
+        self.token_revenue.insert(&token_id, &(revenue - amount.0));
+        transfer.then(
+            Self::ext(env::current_account_id())
+                .with_static_gas(GAS_FOR_CALLBACK)
+                .callback_refund_token_revenue(token_id, amount),
+        )
+    }
+
+    // callback
+    // withdraw_token_revenue()による送信が失敗した場合, 差し引いた使用料を元に戻します.
+    #[private]
+    pub fn callback_refund_token_revenue(&mut self, token_id: AccountId, amount: U128) {
+        assert_eq!(env::promise_results_count(), 1, "This is a callback method");
+        match env::promise_result(0) {
+            PromiseResult::NotReady => unreachable!(),
+            PromiseResult::Failed => {
+                log!(
+                    "Fail cross-contract call, refund {} of {}",
+                    amount.0,
+                    &token_id
+                );
+                self.add_token_revenue(&token_id, amount.0);
+            }
+            PromiseResult::Successful(_) => {}
+        }
+    }
+}
+
+// 点検の報酬などコントラクトからの送信に使用するトークン
+pub(crate) fn base_token_id() -> AccountId {
+    FT_CONTRACT_ACCOUNT.parse().unwrap()
+}
+
+impl Contract {
+    // token_idでバイクを使用するのに必要な使用料.
+    // 所有者がいるバイクは所有者が設定した料金で, FT_CONTRACT_ACCOUNTでのみ支払えます.
+    pub(crate) fn rental_price(&self, index: usize, token_id: &AccountId) -> u128 {
+        let token_price = self
+            .accepted_tokens
+            .get(token_id)
+            .expect("Token is not accepted");
+        match self.bikes[index].owner_id {
+            None => token_price,
+            Some(_) => {
+                assert_eq!(
+                    *token_id,
+                    base_token_id(),
+                    "Bike owned by an account accepts only {}",
+                    FT_CONTRACT_ACCOUNT
+                );
+                self.bikes[index].price
+            }
+        }
+    }
+
+    // バイクの使用を終えた際に, 記録した支払いを支払いに使用された通貨で精算して返します.
+    // 使用料を支払っていない使用ではNoneです.
+    pub(crate) fn settle_payment(&mut self, index: usize) -> Option<Payment> {
+        let payment = self.bikes[index].payment.take()?;
+        match &payment.currency {
+            Currency::Near => self.near_revenue += payment.amount,
+            Currency::Ft(token_id) if *token_id == base_token_id() => {
+                self.treasury.take_held_fee(payment.amount);
+                self.receive_rental_fee(index, payment.amount);
+            }
+            Currency::Ft(token_id) => self.add_token_revenue(token_id, payment.amount),
+        }
+        Some(payment)
+    }
+
+    pub(crate) fn add_token_revenue(&mut self, token_id: &AccountId, amount: u128) {
+        let revenue = self.token_revenue.get(token_id).unwrap_or(0);
+        self.token_revenue.insert(token_id, &(revenue + amount));
+    }
+}
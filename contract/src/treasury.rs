@@ -0,0 +1,352 @@
+use near_sdk::{
+    borsh::{self, BorshDeserialize, BorshSerialize},
+    env,
+    json_types::U128,
+    log, near_bindgen,
+    serde::{Deserialize, Serialize},
+    serde_json, AccountId, Promise, PromiseResult,
+};
+
+use crate::*;
+
+// コントラクトが保有するFTの内訳.
+// 使用料, 点検の報酬, 新規ユーザへの送信のそれぞれに使えるFTを分けて管理します.
+#[derive(BorshDeserialize, BorshSerialize, Default)]
+pub struct Treasury {
+    // バイクの使用料として受け取ったFT. 管理者が引き出せます.
+    revenue: u128,
+    // 点検の報酬のために確保したFT
+    reward_pool: u128,
+    // 新規ユーザへの送信のために確保したFT
+    gift_budget: u128,
+    // 点検中のバイクの報酬として, reward_poolから取り分けたFT
+    committed_rewards: u128,
+    // バイクの所有者が受け取れる使用料の合計
+    owner_earnings: u128,
+    // 紹介者が受け取れる紹介の報酬の合計
+    referral_rewards: u128,
+    // バイクの使用時に受け取った預り金の合計
+    deposits: u128,
+    // 使用中のバイクの使用料として受け取ったFT. 返却時に精算し, 使用料と点検の報酬の資金, 所有者の使用料に分配します.
+    held_fees: u128,
+    // コントラクトが保有するFTの残高.
+    // FTの受信と送信から計算し, sync_ft_balance()でftコントラクトの残高に更新します.
+    ft_balance: u128,
+}
+
+// Treasuryの内訳の種類
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Copy, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum TreasuryAccount {
+    Revenue,
+    RewardPool,
+    GiftBudget,
+    OwnerEarnings,
+    ReferralRewards,
+    Deposits,
+}
+
+// treasury_summary()の返り値
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct TreasurySummary {
+    pub revenue: U128,
+    pub reward_pool: U128,
+    pub gift_budget: U128,
+    pub committed_rewards: U128,
+    pub owner_earnings: U128,
+    pub referral_rewards: U128,
+    pub deposits: U128,
+    pub held_fees: U128,
+    pub ft_balance: U128,
+}
+
+// 使用料の分配の割合(ベーシスポイント, 10000 = 100%)
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct RevenueShare {
+    // 使用料のうち点検の報酬の資金に回す割合. 残りは使用料として管理者が引き出せます.
+    pub reward_pool_bps: u32,
+    // 点検1回の報酬として支払う, 点検の報酬の資金の割合.
+    // 報酬の資金はバイクの使用に応じて増えるため, 報酬もバイクの使用に応じて増減します.
+    pub inspection_reward_bps: u32,
+}
+
+const MAX_BPS: u32 = 10000;
+
+impl TreasuryAccount {
+    // ft_on_transferのmsgが資金の入金を表す場合, 入金先を返します.
+    pub(crate) fn from_funding_msg(msg: &str) -> Option<Self> {
+        match msg {
+            "fund_reward_pool" => Some(Self::RewardPool),
+            "fund_gift_budget" => Some(Self::GiftBudget),
+            _ => None,
+        }
+    }
+}
+
+impl Treasury {
+    pub(crate) fn balance(&self, account: TreasuryAccount) -> u128 {
+        match account {
+            TreasuryAccount::Revenue => self.revenue,
+            TreasuryAccount::RewardPool => self.reward_pool,
+            TreasuryAccount::GiftBudget => self.gift_budget,
+            TreasuryAccount::OwnerEarnings => self.owner_earnings,
+            TreasuryAccount::ReferralRewards => self.referral_rewards,
+            TreasuryAccount::Deposits => self.deposits,
+        }
+    }
+
+    fn balance_mut(&mut self, account: TreasuryAccount) -> &mut u128 {
+        match account {
+            TreasuryAccount::Revenue => &mut self.revenue,
+            TreasuryAccount::RewardPool => &mut self.reward_pool,
+            TreasuryAccount::GiftBudget => &mut self.gift_budget,
+            TreasuryAccount::OwnerEarnings => &mut self.owner_earnings,
+            TreasuryAccount::ReferralRewards => &mut self.referral_rewards,
+            TreasuryAccount::Deposits => &mut self.deposits,
+        }
+    }
+
+    pub(crate) fn deposit(&mut self, account: TreasuryAccount, amount: u128) {
+        *self.balance_mut(account) += amount;
+    }
+
+    pub(crate) fn withdraw(&mut self, account: TreasuryAccount, amount: u128) {
+        let balance = self.balance_mut(account);
+        assert!(*balance >= amount, "Not enough {:?} in treasury", account);
+        *balance -= amount;
+    }
+
+    // コントラクトが受信したFTをaccountに入金します.
+    pub(crate) fn receive(&mut self, account: TreasuryAccount, amount: u128) {
+        self.deposit(account, amount);
+        self.ft_balance += amount;
+    }
+
+    // 使用料を分配の割合に従って点検の報酬の資金と使用料に入金します.
+    pub(crate) fn receive_rental_fee(&mut self, fee: u128, share: &RevenueShare) {
+        let to_reward_pool = fee * share.reward_pool_bps as u128 / MAX_BPS as u128;
+        self.receive(TreasuryAccount::RewardPool, to_reward_pool);
+        self.receive(TreasuryAccount::Revenue, fee - to_reward_pool);
+    }
+
+    // 使用中のバイクの使用料として受け取ったFTを, 返却時の精算まで保持します.
+    pub(crate) fn hold_fee(&mut self, fee: u128) {
+        self.held_fees += fee;
+        self.ft_balance += fee;
+    }
+
+    // 精算する使用料を取り出します. 分配先へreceive()で入金し直すため, 残高からも差し引きます.
+    pub(crate) fn take_held_fee(&mut self, fee: u128) {
+        assert!(self.held_fees >= fee, "Not enough held fees in treasury");
+        self.held_fees -= fee;
+        self.ft_balance -= fee;
+    }
+
+    // コントラクトから送信するFTを残高から差し引きます.
+    fn send(&mut self, amount: u128) {
+        assert!(self.ft_balance >= amount, "Not enough FT balance");
+        self.ft_balance -= amount;
+    }
+
+    // FTの残高のうち, どの内訳にも含まれないFT. ft_on_transferを経ずに送信されたFTです.
+    pub(crate) fn unaccounted(&self) -> u128 {
+        let accounted = self.revenue
+            + self.reward_pool
+            + self.gift_budget
+            + self.committed_rewards
+            + self.owner_earnings
+            + self.referral_rewards
+            + self.deposits
+            + self.held_fees;
+        self.ft_balance.saturating_sub(accounted)
+    }
+
+    // 点検の報酬をreward_poolから取り分けます.
+    // 報酬を支払えない場合は, 点検を始める前にpanicします.
+    pub(crate) fn commit_reward(&mut self, amount: u128) {
+        assert!(amount > 0, "Not enough RewardPool in treasury");
+        self.withdraw(TreasuryAccount::RewardPool, amount);
+        self.committed_rewards += amount;
+        assert!(
+            self.ft_balance >= self.committed_rewards,
+            "Not enough FT balance to cover the reward"
+        );
+    }
+
+    // 報酬を支払わずに点検が終わった場合, 取り分けた報酬をreward_poolに戻します.
+    pub(crate) fn release_reward(&mut self, amount: u128) {
+        self.committed_rewards -= amount;
+        self.deposit(TreasuryAccount::RewardPool, amount);
+    }
+
+    pub(crate) fn pay_reward(&mut self, amount: u128) {
+        self.committed_rewards -= amount;
+        self.send(amount);
+    }
+
+    // 報酬の送信が失敗した場合, 取り分けた状態に戻します.
+    pub(crate) fn refund_reward(&mut self, amount: u128) {
+        self.committed_rewards += amount;
+        self.ft_balance += amount;
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    pub fn treasury_summary(&self) -> TreasurySummary {
+        TreasurySummary {
+            revenue: U128::from(self.treasury.balance(TreasuryAccount::Revenue)),
+            reward_pool: U128::from(self.treasury.balance(TreasuryAccount::RewardPool)),
+            gift_budget: U128::from(self.treasury.balance(TreasuryAccount::GiftBudget)),
+            committed_rewards: U128::from(self.treasury.committed_rewards),
+            owner_earnings: U128::from(self.treasury.owner_earnings),
+            referral_rewards: U128::from(self.treasury.referral_rewards),
+            deposits: U128::from(self.treasury.deposits),
+            held_fees: U128::from(self.treasury.held_fees),
+            ft_balance: U128::from(self.treasury.ft_balance),
+        }
+    }
+
+    pub fn revenue_share(&self) -> RevenueShare {
+        self.revenue_share.clone()
+    }
+
+    // 使用料の分配の割合を設定します. ManageSettingsの権限が必要です.
+    pub fn set_revenue_share(&mut self, revenue_share: RevenueShare) {
+        self.assert_governed(Permission::ManageSettings);
+        assert!(
+            revenue_share.reward_pool_bps <= MAX_BPS
+                && revenue_share.inspection_reward_bps <= MAX_BPS,
+            "Share must not exceed {} basis points",
+            MAX_BPS
+        );
+        log!(
+            "set revenue share: reward pool {}, inspection reward {}",
+            revenue_share.reward_pool_bps,
+            revenue_share.inspection_reward_bps
+        );
+        self.revenue_share = revenue_share;
+    }
+
+    // ftコントラクトのft_balance_ofを呼び出し(cross contract call), FTの残高を更新します. Auditの権限が必要です.
+    // 送信中のFTがある場合は正しい残高にならないため, 送信が完了してから呼び出してください.
+    pub fn sync_ft_balance(&mut self) -> Promise {
+        self.assert_permission(Permission::Audit);
+        ext_ft::ext(base_token_id())
+            .ft_balance_of(env::current_account_id())
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_CALLBACK)
+                    .callback_sync_ft_balance(),
+            )
+    }
+
+    // 使用料として受け取ったFTをreceiver_idへ送信します. ManageTreasuryの権限が必要です.
+    pub fn withdraw_revenue(&mut self, receiver_id: AccountId, amount: U128) -> Promise {
+        self.assert_governed(Permission::ManageTreasury);
+        log!("withdraw revenue to {}: {}", &receiver_id, amount.0);
+        self.transfer_from_treasury(TreasuryAccount::Revenue, receiver_id, amount.0)
+    }
+
+    // 使用料として受け取ったFTを, 点検の報酬もしくは新規ユーザへの送信のために確保します. ManageTreasuryの権限が必要です.
+    pub fn allocate_revenue(&mut self, to: TreasuryAccount, amount: U128) {
+        self.assert_governed(Permission::ManageTreasury);
+        assert!(
+            matches!(
+                to,
+                TreasuryAccount::RewardPool | TreasuryAccount::GiftBudget
+            ),
+            "Revenue can only be allocated to RewardPool or GiftBudget"
+        );
+        log!("allocate revenue to {:?}: {}", to, amount.0);
+        self.treasury.withdraw(TreasuryAccount::Revenue, amount.0);
+        self.treasury.deposit(to, amount.0);
+    }
+
+    // ft_on_transferを経ずに送信され, どの内訳にも含まれないFTを, 点検の報酬もしくは新規ユーザへの送信のために確保します.
+    // ManageTreasuryの権限が必要です. 先にsync_ft_balance()でFTの残高を更新してください.
+    pub fn allocate_unaccounted_ft(&mut self, to: TreasuryAccount) -> U128 {
+        self.assert_governed(Permission::ManageTreasury);
+        assert!(
+            matches!(
+                to,
+                TreasuryAccount::RewardPool | TreasuryAccount::GiftBudget
+            ),
+            "Unaccounted FT can only be allocated to RewardPool or GiftBudget"
+        );
+        let amount = self.treasury.unaccounted();
+        assert!(amount > 0, "No unaccounted FT");
+        log!("allocate unaccounted ft to {:?}: {}", to, amount);
+        self.treasury.deposit(to, amount);
+        U128::from(amount)
+    }
+
+    // callback
+    // transfer_from_treasury()による送信が失敗した場合, 差し引いたFTを元に戻します.
+    // 続けて実行するcallbackのために, 送信が成功したかを返します.
+    #[private]
+    pub fn callback_refund_treasury(&mut self, account: TreasuryAccount, amount: U128) -> bool {
+        assert_eq!(env::promise_results_count(), 1, "This is a callback method");
+        match env::promise_result(0) {
+            PromiseResult::NotReady => unreachable!(),
+            PromiseResult::Failed => {
+                log!(
+                    "Fail cross-contract call, refund {} to {:?}",
+                    amount.0,
+                    account
+                );
+                self.treasury.deposit(account, amount.0);
+                self.treasury.ft_balance += amount.0;
+                false
+            }
+            PromiseResult::Successful(_) => true,
+        }
+    }
+
+    // callback
+    // sync_ft_balance()で取得した残高を記録します.
+    #[private]
+    pub fn callback_sync_ft_balance(&mut self) {
+        assert_eq!(env::promise_results_count(), 1, "This is a callback method");
+        match env::promise_result(0) {
+            PromiseResult::NotReady => unreachable!(),
+            PromiseResult::Failed => panic!("Fail cross-contract call"),
+            PromiseResult::Successful(value) => {
+                let balance: U128 = serde_json::from_slice(&value).unwrap();
+                log!("sync ft balance: {}", balance.0);
+                self.treasury.ft_balance = balance.0;
+            }
+        }
+    }
+}
+
+impl Contract {
+    // 点検1回の報酬: 点検の報酬の資金のうち, inspection_reward_bpsの割合
+    pub(crate) fn inspection_reward(&self) -> u128 {
+        self.treasury.reward_pool * self.revenue_share.inspection_reward_bps as u128
+            / MAX_BPS as u128
+    }
+
+    // Treasuryのaccountから差し引いたFTをreceiver_idへ送信します.
+    pub(crate) fn transfer_from_treasury(
+        &mut self,
+        account: TreasuryAccount,
+        receiver_id: AccountId,
+        amount: u128,
+    ) -> Promise {
+        let transfer = self.cross_contract_call_transfer(
+            &base_token_id(),
+            receiver_id.to_string(),
+            amount.to_string(),
+        );
+        self.treasury.withdraw(account, amount);
+        self.treasury.send(amount);
+        transfer.then(
+            Self::ext(env::current_account_id())
+                .with_static_gas(GAS_FOR_CALLBACK)
+                .callback_refund_treasury(account, U128::from(amount)),
+        )
+    }
+}
@@ -10,6 +10,7 @@ import {
   ft_balance_of,
   storage_balance_of,
   storage_deposit,
+  bike_storage_deposit,
   ft_transfer,
   ft_transfer_call,
   is_available,
@@ -150,6 +151,7 @@ export default function App() {
     try {
       await storage_deposit().then(async (value) => {
         console.log("Result of storage_deposit: ", value);
+        await bike_storage_deposit();
         await transfer_ft_to_new_user(window.accountId);
       });
     } catch (e) {
@@ -2,6 +2,8 @@ const BIKE_CONTRACT_NAME =
   process.env.CONTRACT_NAME || "sub.bike_share.testnet";
 // my_ftに関しては簡単のために固定で用意
 const FT_CONTRACT_NAME = "my_ft.testnet";
+// バイクを返却するステーションに関しても簡単のために固定で用意(new()で登録されるステーション)
+const STATION_ID = "default";
 
 // 変更点メモ
 // 1. bikeContractNameをftContractNameとbikeContractNameの二つに変更
@@ -15,6 +17,7 @@ function getConfig(env) {
         nodeUrl: "https://rpc.mainnet.near.org",
         bikeContractName: BIKE_CONTRACT_NAME,
         ftContractName: FT_CONTRACT_NAME,
+        stationId: STATION_ID,
         walletUrl: "https://wallet.near.org",
         helperUrl: "https://helper.mainnet.near.org",
         explorerUrl: "https://explorer.mainnet.near.org",
@@ -26,6 +29,7 @@ function getConfig(env) {
         nodeUrl: "https://rpc.testnet.near.org",
         bikeContractName: BIKE_CONTRACT_NAME,
         ftContractName: FT_CONTRACT_NAME,
+        stationId: STATION_ID,
         walletUrl: "https://wallet.testnet.near.org",
         helperUrl: "https://helper.testnet.near.org",
         explorerUrl: "https://explorer.testnet.near.org",
@@ -36,6 +40,7 @@ function getConfig(env) {
         nodeUrl: "https://rpc.betanet.near.org",
         bikeContractName: BIKE_CONTRACT_NAME,
         ftContractName: FT_CONTRACT_NAME,
+        stationId: STATION_ID,
         walletUrl: "https://wallet.betanet.near.org",
         helperUrl: "https://helper.betanet.near.org",
         explorerUrl: "https://explorer.betanet.near.org",
@@ -48,6 +53,7 @@ function getConfig(env) {
         walletUrl: "http://localhost:4000/wallet",
         bikeContractName: BIKE_CONTRACT_NAME,
         ftContractName: FT_CONTRACT_NAME,
+        stationId: STATION_ID,
       };
     case "test":
     case "ci":
@@ -56,6 +62,7 @@ function getConfig(env) {
         nodeUrl: "https://rpc.ci-testnet.near.org",
         bikeContractName: BIKE_CONTRACT_NAME,
         ftContractName: FT_CONTRACT_NAME,
+        stationId: STATION_ID,
         masterAccount: "test.near",
       };
     case "ci-betanet":
@@ -64,6 +71,7 @@ function getConfig(env) {
         nodeUrl: "https://rpc.ci-betanet.near.org",
         bikeContractName: BIKE_CONTRACT_NAME,
         ftContractName: FT_CONTRACT_NAME,
+        stationId: STATION_ID,
         masterAccount: "test.near",
       };
     default:
@@ -39,7 +39,12 @@ export async function initContract() {
         "amount_reward_for_inspections",
       ],
       // Change methods can modify the state. But you don't receive the returned value when called.
-      changeMethods: ["return_bike", "inspect_bike", "transfer_ft_to_new_user"],
+      changeMethods: [
+        "return_bike",
+        "inspect_bike",
+        "transfer_ft_to_new_user",
+        "storage_deposit",
+      ],
     }
   );
 
@@ -128,6 +133,7 @@ export async function inspect_bike(index) {
 export async function return_bike(index) {
   let response = await window.bikeContract.return_bike({
     index: index,
+    location: { Station: nearConfig.stationId },
   });
   return response;
 }
@@ -155,6 +161,15 @@ export async function storage_deposit() {
   return response;
 }
 
+export async function bike_storage_deposit() {
+  let response = await window.bikeContract.storage_deposit(
+    {}, // 引数の省略: このメソッドを呼び出しているアカウントを登録
+    "300000000000000", // ガスの制限(in gas units)
+    "100000000000000000000000" // デポジット (in yoctoNEAR), バイクの使用などの記録に使用します
+  );
+  return response;
+}
+
 export async function storage_unregister() {
   let response = await window.ftContract.storage_unregister(
     { force: true }, // アカウントの情報に関わらず登録を解除する, 所持しているftはバーンされる
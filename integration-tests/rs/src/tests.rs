@@ -9,6 +9,8 @@ const FT_CONTRACT_ACCOUNT: &str = "my_ft.testnet";
 
 const FT_TOTAL_SUPPLY: u128 = 1000;
 const AMOUNT_TO_USE_BIKE: u128 = 30;
+// new()で登録されるステーション
+const STATION_ID: &str = "default";
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
@@ -53,6 +55,15 @@ async fn main() -> anyhow::Result<()> {
         }))?
         .transact()
         .await?;
+    // 点検の最短時間と点検者の待機時間を無効化
+    bike_contract
+        .call(&worker, "set_inspection_rules")
+        .args_json(serde_json::json!({
+            "min_inspection_duration": "0",
+            "inspector_cooldown": "0"
+        }))?
+        .transact()
+        .await?;
     bike_contract
         .as_account()
         .call(&worker, ft_contract.id(), "storage_deposit")
@@ -141,6 +152,13 @@ async fn test_transfer_call_to_use_bike(
         .transact()
         .await?;
 
+    // user, bikeコントラクトのstorage registory
+    user.call(&worker, bike_contract.id(), "storage_deposit")
+        .args_json(serde_json::json!({}))?
+        .deposit(parse_near!("0.1 N"))
+        .transact()
+        .await?;
+
     // userのFTの用意
     // FTコントラクトからユーザにFTを送信
     owner
@@ -179,6 +197,7 @@ async fn test_transfer_call_to_use_bike(
     user.call(&worker, bike_contract.id(), "return_bike")
         .args_json(serde_json::json!({
             "index": test_bike_index,
+            "location": { "Station": STATION_ID },
         }))?
         .gas(300000000000000)
         .transact()
@@ -214,7 +233,6 @@ async fn test_transfer_ft_to_user_inspected_bike(
     bike_contract: &Contract,
     worker: &Worker<Sandbox>,
 ) -> anyhow::Result<()> {
-    let remuneration_amount = 15;
     let test_bike_index = 0;
 
     // user, storage registory
@@ -227,15 +245,24 @@ async fn test_transfer_ft_to_user_inspected_bike(
         .transact()
         .await?;
 
+    // user, bikeコントラクトのstorage registory
+    user.call(&worker, bike_contract.id(), "storage_deposit")
+        .args_json(serde_json::json!({}))?
+        .deposit(parse_near!("0.1 N"))
+        .transact()
+        .await?;
+
     // bikeコントラクトのFTの用意
-    // FTコントラクトからFTを送信
+    // FTコントラクトからFTを点検の報酬の資金として送信
     owner
-        .call(&worker, ft_contract.id(), "ft_transfer")
+        .call(&worker, ft_contract.id(), "ft_transfer_call")
         .args_json(serde_json::json!({
             "receiver_id": bike_contract.id(),
-            "amount": "50".to_string()
+            "amount": "50".to_string(),
+            "msg": "fund_reward_pool",
         }))?
         .deposit(1)
+        .gas(300000000000000)
         .transact()
         .await?;
 
@@ -248,6 +275,14 @@ async fn test_transfer_ft_to_user_inspected_bike(
         .json()?;
     assert_eq!(user_balance.0, 0);
 
+    // 点検の報酬は点検の報酬の資金に応じて決まるため, 点検を始める前に確認
+    let remuneration_amount: U128 = bike_contract
+        .call(&worker, "amount_reward_for_inspections")
+        .args_json(json!({}))?
+        .transact()
+        .await?
+        .json()?;
+
     // inspect_bike()の呼び出し
     user.call(&worker, bike_contract.id(), "inspect_bike")
         .args_json(serde_json::json!({
@@ -270,6 +305,7 @@ async fn test_transfer_ft_to_user_inspected_bike(
     user.call(&worker, bike_contract.id(), "return_bike")
         .args_json(serde_json::json!({
             "index": test_bike_index,
+            "location": { "Station": STATION_ID },
         }))?
         .gas(300000000000000)
         .transact()
@@ -282,7 +318,7 @@ async fn test_transfer_ft_to_user_inspected_bike(
         .transact()
         .await?
         .json()?;
-    assert_eq!(user_balance.0, remuneration_amount);
+    assert_eq!(user_balance.0, remuneration_amount.0);
 
     println!("      Passed ✅ test_transfer_ft_to_user_inspected_bike");
     Ok(())